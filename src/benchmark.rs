@@ -0,0 +1,205 @@
+use crate::{
+    format_performance,
+    random_starts,
+    DesignSpace,
+    Precision,
+    PRECISION,
+};
+use crate::minimize::{axis_simplex_centered_at, minimize};
+use std::cell::Cell;
+use std::fmt;
+
+/// A function to minimize, and the space where its minimum should be looked for.
+pub struct Objective<'f> {
+    pub name: &'static str,
+    pub f: &'f dyn Fn([f32; 2]) -> f32,
+    pub space: DesignSpace,
+}
+
+/// A way to look for the minimum of a function in a space, using at most about `budget`
+/// evaluations. An optimizer that goes over the budget doesn't gain anything from it, since only
+/// the first `budget` evaluations count towards the best value found.
+pub struct Optimizer {
+    pub name: &'static str,
+    pub run: fn(f: &dyn Fn([f32; 2]) -> f32, space: DesignSpace, budget: usize),
+}
+
+/// Nelder–Mead, starting from an axis aligned simplex covering the middle of the space, with
+/// enough iterations to use up the budget.
+pub const NELDER_MEAD: Optimizer = Optimizer {
+    name: "Nelder-Mead",
+    run: |f, space, budget| {
+        let center = [(space.x1.0 + space.x1.1) / 2., (space.x2.0 + space.x2.1) / 2.];
+        let step = [(space.x1.1 - space.x1.0) / 4., (space.x2.1 - space.x2.0) / 4.];
+
+        // Each iteration evaluates four trial points, (or more, if it shrinks.)
+        let iters = (budget / 4).min(u16::MAX as usize) as u16;
+
+        minimize(f, axis_simplex_centered_at(center, step), iters);
+    },
+};
+
+/// Evaluates uniformly random points in the space, and nothing else.
+pub const RANDOM_SEARCH: Optimizer = Optimizer {
+    name: "random search",
+    run: |f, space, budget| {
+        for [x1, x2] in random_starts(space, budget as u32, <_>::default()) {
+            f([x1, x2]);
+        }
+    },
+};
+
+/// The best value each optimizer found for each objective.
+#[derive(Debug)]
+pub struct Report {
+    pub objectives: Vec<&'static str>,
+    pub optimizers: Vec<&'static str>,
+    /// `best[i][j]` is the best value `optimizers[j]` found for `objectives[i]`, or infinity if it
+    /// didn't evaluate the objective at all.
+    pub best: Vec<Vec<f32>>,
+    /// How many places of each best value the table shows.
+    pub precision: Precision,
+}
+
+impl Report {
+    /// The optimizer that found the best value for the most objectives, with ties going to the
+    /// one listed first. `None` if there are no optimizers.
+    pub fn most_wins(&self) -> Option<&'static str> {
+        let mut wins = vec![0; self.optimizers.len()];
+
+        for row in &self.best {
+            let winner = (0..row.len()).min_by(|&a, &b| row[a].total_cmp(&row[b]));
+            if let Some(winner) = winner {
+                wins[winner] += 1;
+            }
+        }
+
+        (0..wins.len())
+            .max_by(|&a, &b| wins[a].cmp(&wins[b]).then(b.cmp(&a)))
+            .map(|i| self.optimizers[i])
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<24}", "")?;
+        for name in &self.optimizers {
+            write!(f, "{name:>16}")?;
+        }
+
+        for (objective, row) in self.objectives.iter().zip(&self.best) {
+            write!(f, "\n{objective:<24}")?;
+            for best in row {
+                write!(f, "{:>16}", format_performance(*best, self.precision))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs each of `optimizers` on each of `objectives`, counting only the first `budget`
+/// evaluations of each run, and tabulates the best value each found. Since every optimizer gets
+/// the same number of evaluations, the comparison is fair no matter how much each one costs per
+/// step, and running this again after a change to an optimizer shows whether it got any worse.
+pub fn benchmark_optimizers(
+    objectives: &[Objective],
+    optimizers: &[Optimizer],
+    budget: usize,
+) -> Report {
+    let mut best = Vec::with_capacity(objectives.len());
+
+    for objective in objectives {
+        let mut row = Vec::with_capacity(optimizers.len());
+
+        for optimizer in optimizers {
+            let evaluations = Cell::new(0);
+            let best_found = Cell::new(f32::INFINITY);
+
+            let counted = |xs: [f32; 2]| {
+                let y = (objective.f)(xs);
+                if evaluations.get() < budget {
+                    evaluations.set(evaluations.get() + 1);
+                    best_found.set(best_found.get().min(y));
+                }
+                y
+            };
+
+            (optimizer.run)(&counted, objective.space, budget);
+
+            row.push(best_found.get());
+        }
+
+        best.push(row);
+    }
+
+    Report {
+        objectives: objectives.iter().map(|o| o.name).collect(),
+        optimizers: optimizers.iter().map(|o| o.name).collect(),
+        best,
+        precision: PRECISION,
+    }
+}
+
+#[cfg(test)]
+mod benchmark_optimizers_works {
+    use super::*;
+
+    const UNIT_SQUARE: DesignSpace = DesignSpace { x1: (-1., 1.), x2: (-1., 1.) };
+
+    #[test]
+    fn tabulates_each_optimizer_on_each_objective() {
+        let bowl = |[x, y]: [f32; 2]| x * x + y * y;
+        let shifted = |[x, y]: [f32; 2]| (x - 0.5) * (x - 0.5) + y * y + 1.;
+
+        let report = benchmark_optimizers(
+            &[
+                Objective { name: "bowl", f: &bowl, space: UNIT_SQUARE },
+                Objective { name: "shifted", f: &shifted, space: UNIT_SQUARE },
+            ],
+            &[NELDER_MEAD, RANDOM_SEARCH],
+            200,
+        );
+
+        assert_eq!(report.objectives, ["bowl", "shifted"]);
+        assert_eq!(report.optimizers, ["Nelder-Mead", "random search"]);
+        assert!(report.best[0][0] < 1e-4, "{report}");
+        assert!(report.best[1][0] - 1. < 1e-4, "{report}");
+        assert!(report.best[0][1] < 0.1 && report.best[1][1] - 1. < 0.1, "{report}");
+        assert_eq!(report.most_wins(), Some("Nelder-Mead"));
+    }
+
+    #[test]
+    fn only_counts_the_budget() {
+        const LATE_BLOOMER: Optimizer = Optimizer {
+            name: "late bloomer",
+            run: |f, _, _| {
+                f([1., 1.]);
+                f([1., 1.]);
+                f([0., 0.]);
+            },
+        };
+
+        let report = benchmark_optimizers(
+            &[Objective { name: "bowl", f: &|[x, y]| x * x + y * y, space: UNIT_SQUARE }],
+            &[LATE_BLOOMER],
+            2,
+        );
+
+        assert_eq!(report.best, [[2.]]);
+    }
+
+    #[test]
+    fn displays_the_best_values_at_the_reports_precision() {
+        let mut report = Report {
+            objectives: vec!["bowl"],
+            optimizers: vec!["Nelder-Mead"],
+            best: vec![vec![0.125]],
+            precision: Precision::Decimals(2),
+        };
+        assert!(report.to_string().ends_with("            0.12"), "{report}");
+
+        report.precision = Precision::Full;
+        assert!(report.to_string().ends_with("           0.125"), "{report}");
+    }
+}
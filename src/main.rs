@@ -1,9 +1,24 @@
 #![allow(non_snake_case)] // Keep the names from the article.
 
+mod benchmark;
 mod minimize;
+mod repl;
+mod spec;
+mod stats;
 mod xs;
 
-use minimize::{minimize, regular_simplex_centered_at};
+use minimize::{
+    axis_simplex_centered_at,
+    lexicographic,
+    minimize,
+    minimize_by,
+    minimize_multistart,
+    minimize_on_lattice,
+    minimize_recording,
+    minimize_scaled,
+    minimize_with_simplex,
+    regular_simplex_centered_at,
+};
 use xs::{Seed};
 
 const ANNUAL_FORTNIGHTS: u8 = 26;
@@ -16,19 +31,19 @@ struct Account {
 }
 
 impl Account {
-    fn deposit(&mut self, amount: Money) {
-        self.balance = self.balance + amount;
-    }
-
-    fn withdraw(&mut self, amount: Money) {
-        self.balance = self.balance - amount;
+    // Saturate rather than overflow, so long horizons pin to the limits instead of panicking.
+    fn change_by(&mut self, signed_amount: Money) {
+        self.balance = self.balance.saturating_add(signed_amount);
     }
 }
 
 #[derive(Clone, Copy)]
 enum Kind {
     Deposit,
-    Withdraw
+    Withdraw,
+    /// Leaves the balance as it is. For periods where nothing happens, without that counting as a
+    /// transaction the way a zero amount deposit would.
+    NoOp,
 }
 
 #[derive(Clone, Copy)]
@@ -37,6 +52,19 @@ struct Transaction {
     amount: Money
 }
 
+impl Transaction {
+    /// The effect this transaction has on the balance: `amount` for a deposit, `-amount` for a
+    /// withdrawal, and 0 for a no-op. Anything that needs the direction of a transaction should go
+    /// through this, so that there is only one place that decides it.
+    fn signed_amount(&self) -> Money {
+        match self.kind {
+            Kind::Deposit => self.amount,
+            Kind::Withdraw => self.amount.saturating_neg(),
+            Kind::NoOp => 0,
+        }
+    }
+}
+
 macro_rules! t {
     (d, $amount: expr) => {
         Transaction {
@@ -50,33 +78,234 @@ macro_rules! t {
             amount: $amount,
         }
     };
+    (n) => {
+        Transaction {
+            kind: Kind::NoOp,
+            amount: 0,
+        }
+    };
+}
+
+fn simulate_transaction(account: &mut Account, transaction: Transaction) {
+    account.change_by(transaction.signed_amount());
+}
+
+/// The number of transactions that actually are transactions, that is, not counting no-ops.
+fn count_transactions(transactions: &[Transaction]) -> usize {
+    transactions
+        .iter()
+        .filter(|t| !matches!(t.kind, Kind::NoOp))
+        .count()
+}
+
+/// Buckets the transactions into consecutive groups of `period_length`, (the last group may be
+/// shorter,) and replaces each group with a single transaction with the same net effect. A group
+/// that nets out to nothing becomes a no-op. For example, for turning daily transactions into
+/// fortnightly ones, with a `period_length` of 14. Panics if `period_length` is 0.
+fn resample_transactions(transactions: &[Transaction], period_length: usize) -> Vec<Transaction> {
+    transactions
+        .chunks(period_length)
+        .map(|bucket| {
+            let net = bucket.iter().fold(0 as Money, |net, t| net.saturating_add(t.signed_amount()));
+
+            match net {
+                0 => t!(n),
+                n if n > 0 => t!(d, n),
+                n => t!(w, n.saturating_neg()),
+            }
+        })
+        .collect()
+}
+
+/// Where a transaction came from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Source {
+    /// Planned, as part of the design.
+    Design,
+    /// Outside the plan; something the environment did.
+    Environment,
 }
 
-fn simulate_transaction(account: &mut Account, Transaction { kind, amount }: Transaction) {
-    use Kind::*;
-    match kind {
-        Deposit => account.deposit(amount),
-        Withdraw => account.withdraw(amount),
+#[derive(Clone, Copy)]
+struct TaggedTransaction {
+    txn: Transaction,
+    source: Source,
+}
+
+impl From<TaggedTransaction> for Transaction {
+    fn from(tagged: TaggedTransaction) -> Self {
+        tagged.txn
     }
 }
 
-fn simulate_balance(transactions: &[Transaction]) -> Vec<Money> {
-    let mut account = Account::default();
+/// Accepts plain or tagged transactions. The tags make no difference to the balances.
+fn simulate_balance<T: Copy + Into<Transaction>>(transactions: &[T]) -> Vec<Money> {
+    simulate_balance_from(Account::default().balance, transactions)
+}
+
+fn simulate_balance_from<T: Copy + Into<Transaction>>(initial_balance: Money, transactions: &[T]) -> Vec<Money> {
+    let mut account = Account { balance: initial_balance };
     let mut balances = vec![account.balance];
     for &t in transactions {
-        simulate_transaction(&mut account, t);
+        simulate_transaction(&mut account, t.into());
         balances.push(account.balance);
     }
 
-    return balances
+    balances
+}
+
+/// How far the balances went outside the limits given to `simulate_balance_constrained`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Violations {
+    /// The number of balances outside of the limits.
+    count: usize,
+    /// The sum over those balances of how far outside the limits each one was.
+    total_excursion: i64,
+}
+
+/// Like `simulate_balance`, but for an account whose terms say the balance should stay between
+/// `floor` and `ceiling`, inclusive, such as a minimum balance to avoid fees. The transactions
+/// still all go through as usual, but each balance after a transaction that is outside the limits
+/// is recorded in the returned `Violations`, to be penalized by a metric such as
+/// `translate_performance_ViolationPenalty`. The initial balance is from before the account's
+/// terms apply, so it is never counted as a violation. Panics if `floor` is above `ceiling`, since
+/// then no balance could meet the terms.
+fn simulate_balance_constrained<T: Copy + Into<Transaction>>(
+    transactions: &[T],
+    floor: Money,
+    ceiling: Money,
+) -> (Vec<Money>, Violations) {
+    assert!(floor <= ceiling, "the floor, {floor}, is above the ceiling, {ceiling}");
+
+    let balances = simulate_balance(transactions);
+
+    let mut violations = Violations::default();
+    for &balance in &balances[1..] {
+        let excursion = if balance < floor {
+            floor as i64 - balance as i64
+        } else if balance > ceiling {
+            balance as i64 - ceiling as i64
+        } else {
+            continue
+        };
+
+        violations.count += 1;
+        violations.total_excursion += excursion;
+    }
+
+    (balances, violations)
+}
+
+/// Folds `f` over the balances `simulate_balance` would return, starting with the initial balance,
+/// without allocating them. For metrics that only need a single pass over the balances.
+fn fold_balance<T>(transactions: &[Transaction], init: T, f: impl Fn(T, Money) -> T) -> T {
+    let mut account = Account::default();
+    let mut output = f(init, account.balance);
+
+    for &t in transactions {
+        simulate_transaction(&mut account, t);
+        output = f(output, account.balance);
+    }
+
+    output
+}
+
+#[derive(Debug, PartialEq)]
+enum TransactionError {
+    /// There were more than `cap` transactions.
+    CapExceeded { cap: usize },
+}
+
+/// A problem with a transaction list, as found by `validate_transactions`.
+#[derive(Debug, PartialEq)]
+enum ValidationError {
+    /// The transaction at `index` has a negative amount. Amounts are magnitudes, with the direction
+    /// given by the `Kind`, so a negative deposit is really a withdrawal in disguise, or vice versa.
+    SignContradictsKind { index: usize },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::SignContradictsKind { index } => write!(
+                f,
+                "transaction {index} has a negative amount, which contradicts its kind"
+            ),
+        }
+    }
+}
+
+/// Checks the transactions are well formed, returning the first problem found if not.
+fn validate_transactions(transactions: &[Transaction]) -> Result<(), ValidationError> {
+    for (index, t) in transactions.iter().enumerate() {
+        if t.amount < 0 && !matches!(t.kind, Kind::NoOp) {
+            return Err(ValidationError::SignContradictsKind { index })
+        }
+    }
+
+    Ok(())
+}
+
+/// Like `simulate_balance`, but takes the transactions one at a time, and hands each balance,
+/// starting with the initial one, to `on_balance` instead of collecting them. So the transactions
+/// can be generated lazily, and only the current one needs to be in memory. Stops with an error,
+/// without pulling any more transactions, on seeing more than `cap` of them. Otherwise returns the
+/// final balance.
+fn simulate_balance_streaming(
+    transactions: impl IntoIterator<Item = Transaction>,
+    cap: usize,
+    mut on_balance: impl FnMut(Money),
+) -> Result<Money, TransactionError> {
+    let mut account = Account::default();
+    on_balance(account.balance);
+
+    for (i, t) in transactions.into_iter().enumerate() {
+        if i >= cap {
+            return Err(TransactionError::CapExceeded { cap })
+        }
+        simulate_transaction(&mut account, t);
+        on_balance(account.balance);
+    }
+
+    Ok(account.balance)
+}
+
+/// The index of the first transaction that leaves the balance below zero, or `None` if the balance
+/// never goes below zero. Stops simulating at that point, since nothing after ruin matters.
+fn simulate_until_ruin(transactions: &[Transaction]) -> Option<usize> {
+    let mut account = Account::default();
+
+    for (i, &t) in transactions.iter().enumerate() {
+        simulate_transaction(&mut account, t);
+        if account.balance < 0 {
+            return Some(i)
+        }
+    }
+
+    None
+}
+
+/// Given the `balances` from `simulate_balance`, returns what they would be if the effect of the
+/// transaction at `changed_index` on the balance had changed by `delta`. So a withdrawal shrinking
+/// by 5 is a `delta` of 5. Since every later balance just shifts by `delta`, this only touches the
+/// balances from that transaction onwards.
+#[allow(unused)]
+fn update_balances(mut balances: Vec<Money>, changed_index: usize, delta: Money) -> Vec<Money> {
+    // `balances[0]` is the balance before any transactions.
+    for balance in &mut balances[changed_index + 1..] {
+        *balance += delta;
+    }
+
+    balances
 }
 
 type Performance = f32;
 
 fn translate_performance_TargetBalance(balances: &[Money], target: Money) -> Performance {
-    let mut sum = 0;
-    for b in balances {
-        sum += (b - target).abs();
+    // Wide enough that no `Money` difference, or sum of a long run of them, can overflow.
+    let mut sum: i64 = 0;
+    for &b in balances {
+        sum += (b as i64 - target as i64).abs();
     }
     (sum as Performance) / (balances.len() as Performance)
 }
@@ -85,6 +314,55 @@ fn translate_performance_Target100(balances: &[Money]) -> Performance {
     translate_performance_TargetBalance(balances, 100)
 }
 
+/// A cost of `per_violation` for each balance outside the account's limits, plus `per_dollar` for
+/// each dollar outside them. For adding to one of the other metrics, so that designs that break the
+/// account's terms come out worse, in proportions set by how the account's fees work.
+fn translate_performance_ViolationPenalty(violations: Violations, per_violation: f32, per_dollar: f32) -> Performance {
+    violations.count as Performance * per_violation + violations.total_excursion as Performance * per_dollar
+}
+
+/// A cost of `per_txn_cost` for each transaction, not counting no-ops. Unlike the other metrics,
+/// this looks at the transactions rather than the balances, for adding to one of those, so that
+/// designs that only do well by making many small transactions, which would be a hassle to follow,
+/// come out worse than simpler ones that do nearly as well.
+fn translate_performance_TransactionCountPenalty(transactions: &[Transaction], per_txn_cost: f32) -> Performance {
+    count_transactions(transactions) as Performance * per_txn_cost
+}
+
+type PerformanceTranslator = fn (balances: &[Money]) -> Performance;
+
+/// The autocorrelation of the balances at each lag from `0` to `max_lag`, using the usual estimator
+/// that divides every lag by the same overall sum of squares. Lags that don't fit in the series are
+/// left off. So how "sticky" the balance is: near 1 when each balance mostly follows on from the
+/// ones before it, near 0 when it has little to do with them.
+///
+/// A constant series has no variance to divide by, making the autocorrelation undefined. We return
+/// 1 for every lag in that case since a constant balance is as sticky as it gets.
+fn autocorrelation(balances: &[Money], max_lag: usize) -> Vec<f32> {
+    let lags = max_lag.min(balances.len().saturating_sub(1));
+    if balances.is_empty() {
+        return Vec::new()
+    }
+
+    let mean = balances.iter().map(|&b| b as f32).sum::<f32>() / balances.len() as f32;
+    let deviations = balances.iter().map(|&b| b as f32 - mean).collect::<Vec<_>>();
+
+    let sum_of_squares = deviations.iter().map(|d| d * d).sum::<f32>();
+    if sum_of_squares == 0. {
+        return vec![1.; lags + 1]
+    }
+
+    (0..=lags)
+        .map(|lag| {
+            deviations
+                .iter()
+                .zip(&deviations[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>() / sum_of_squares
+        })
+        .collect()
+}
+
 type DesignParameters = (Money, Money);
 
 macro_rules! p {
@@ -111,41 +389,319 @@ fn translate_design_InitialAndFortnightlyDeposit(design_parameters: DesignParame
 }
 
 fn performance_of_design(design_translator: DesignTranslator, design_parameters: DesignParameters) -> Performance {
-  return translate_performance_Target100(
+    translate_performance_Target100(
         &simulate_balance(
             &design_translator(design_parameters)
         )
     )
 }
 
+/// `metric` of the design's balances, with `translate_performance_TransactionCountPenalty` added, so
+/// that between two designs that do equally well by `metric`, the one with fewer transactions is
+/// better.
+fn performance_of_design_penalized(
+    design_translator: DesignTranslator,
+    design_parameters: DesignParameters,
+    metric: PerformanceTranslator,
+    per_txn_cost: f32,
+) -> Performance {
+    let transactions = design_translator(design_parameters);
+
+    metric(&simulate_balance(&transactions))
+        + translate_performance_TransactionCountPenalty(&transactions, per_txn_cost)
+}
+
+/// Upper bounds on the mean abs delta for each grade, in increasing order.
+const GRADES: [(Performance, &str); 3] = [
+    (5., "excellent"),
+    (20., "good"),
+    (Performance::INFINITY, "poor"),
+];
+
+/// The label of the first of `thresholds` that `value` is at most, where `thresholds` are in
+/// increasing order. Anything past the last threshold, including `NaN`, gets the last label.
+fn grade_performance<'thresholds>(
+    value: Performance,
+    thresholds: &[(Performance, &'thresholds str)],
+) -> &'thresholds str {
+    for &(threshold, label) in thresholds {
+        if value <= threshold {
+            return label
+        }
+    }
+
+    thresholds.last().map(|&(_, label)| label).unwrap_or("")
+}
+
+/// A continuous stand in for `performance_of_design`, for optimizers that get stuck on the flat
+/// steps the rounding to whole `Money` amounts otherwise makes. Each parameter is linearly
+/// interpolated between the performances of the whole amounts either side of it, (so bilinearly,
+/// for both parameters together). At whole amounts this matches `performance_of_design` exactly.
+fn performance_of_design_smooth(
+    design_translator: DesignTranslator,
+    (x1, x2): (f32, f32),
+) -> Performance {
+    let (floor1, floor2) = (x1.floor(), x2.floor());
+    let (t1, t2) = (x1 - floor1, x2 - floor2);
+    let (floor1, floor2) = (floor1 as Money, floor2 as Money);
+
+    let performance = |d1, d2| performance_of_design(design_translator, p!(floor1 + d1, floor2 + d2));
+
+    let mut output = (1. - t1) * (1. - t2) * performance(0, 0);
+    // Skip the evaluations whose weight is zero.
+    if t1 > 0. {
+        output += t1 * (1. - t2) * performance(1, 0);
+    }
+    if t2 > 0. {
+        output += (1. - t1) * t2 * performance(0, 1);
+    }
+    if t1 > 0. && t2 > 0. {
+        output += t1 * t2 * performance(1, 1);
+    }
+
+    output
+}
+
+/// Of the whole amount designs at the corners of the cell containing `(x1, x2)`, the one with the
+/// best `performance_of_design`. For snapping the result of optimizing
+/// `performance_of_design_smooth` back to a design that can actually be used.
+fn best_whole_design_near(
+    design_translator: DesignTranslator,
+    (x1, x2): (f32, f32),
+) -> DesignParameters {
+    let (floor1, floor2) = (x1.floor() as Money, x2.floor() as Money);
+
+    [p!(floor1, floor2), p!(floor1 + 1, floor2), p!(floor1, floor2 + 1), p!(floor1 + 1, floor2 + 1)]
+        .into_iter()
+        .min_by(|&a, &b| {
+            performance_of_design(design_translator, a)
+                .partial_cmp(&performance_of_design(design_translator, b))
+                .expect("should have no NaNs")
+        })
+        .expect("there are four candidates")
+}
+
+/// How much `metric` improves, (that is, goes down,) from raising design parameter number
+/// `parameter` by one dollar, at the given design point. For the fortnightly deposit, that is
+/// parameter 0 of `translate_design_FortnightlyDeposit`, and parameter 1 of
+/// `translate_design_InitialAndFortnightlyDeposit`. A one dollar finite difference, rather than a
+/// derivative, since deposits are whole dollars. Positive means depositing more would help, and
+/// negative means it would hurt, such as once the balance would overshoot a target. Panics if
+/// `parameter` is not 0 or 1.
+fn marginal_performance_per_dollar(
+    design_translator: DesignTranslator,
+    design_parameters: DesignParameters,
+    parameter: usize,
+    metric: PerformanceTranslator,
+) -> Performance {
+    let (a, b) = design_parameters;
+    let one_more = match parameter {
+        0 => p!(a.saturating_add(1), b),
+        1 => p!(a, b.saturating_add(1)),
+        _ => panic!("there are only two design parameters, not {}", parameter + 1),
+    };
+
+    metric(&simulate_balance(&design_translator(design_parameters)))
+        - metric(&simulate_balance(&design_translator(one_more)))
+}
+
+/// How many decimal places to show a performance to.
+#[derive(Clone, Copy, Debug)]
+enum Precision {
+    Decimals(usize),
+    /// As many as it takes for the exact same `Performance` to be read back.
+    Full,
+}
+
+/// The precision for reports that aren't passed one.
+const PRECISION: Precision = Precision::Decimals(2);
+
+/// All the reports go through this, so they all agree on how a given precision looks.
+fn format_performance(value: Performance, precision: Precision) -> String {
+    match precision {
+        Precision::Decimals(places) => format!("{value:.places$}"),
+        Precision::Full => format!("{value}"),
+    }
+}
+
 macro_rules! evaluate {
     ($design_translator: ident, $design_parameters: expr) => {
+        evaluate!($design_translator, $design_parameters, PRECISION)
+    };
+    ($design_translator: ident, $design_parameters: expr, $precision: expr) => {
         println!("\nevaluating account balance target 100");
         println!("with {} {:?}", stringify!($design_translator), $design_parameters);
-        println!("the mean abs delta is {:.2}", performance_of_design($design_translator, $design_parameters));
+        let performance = performance_of_design($design_translator, $design_parameters);
+        println!(
+            "the mean abs delta is {} ({})",
+            format_performance(performance, $precision),
+            grade_performance(performance, &GRADES)
+        );
     }
 }
 
 
-fn sample_performance_of_design(design_translator: DesignTranslator, design_variants: &[Money]) -> Vec<Performance> {
+/// Every design variant runs against the same `environment`, seed included. That way, under a
+/// random environment, the differences between the performances are due to the designs alone,
+/// rather than partly to each design getting luckier or unluckier withdrawals. (This is the
+/// variance reduction technique known as common random numbers.)
+fn sample_performance_of_design(
+    design_translator: DesignTranslator,
+    environment: Environment,
+    design_variants: &[Money],
+) -> Vec<Performance> {
+    design_variants
+        .iter()
+        .map(|&m| performance_of_design_in_environment(design_translator, p!(m), environment))
+        .collect()
+}
+
+/// Unlike `sample_performance_of_design`, every design variant gets its own independent stream of
+/// randomness, the `i`th variant's environment being seeded with `xs::split(base_seed, i)`. For
+/// when what's wanted is the spread over many different environments, rather than a comparison of
+/// the designs under one.
+fn sample_performance_of_design_split(
+    design_translator: DesignTranslator,
+    environment: Environment,
+    design_variants: &[Money],
+    base_seed: Seed,
+) -> Vec<Performance> {
     design_variants
         .iter()
-        .map(|&m| performance_of_design(design_translator, p!(m)))
+        .enumerate()
+        .map(|(i, &m)| performance_of_design_in_environment(
+            design_translator,
+            p!(m),
+            environment.with_seed(xs::split(base_seed, i as u64))
+        ))
         .collect()
 }
 
-fn visualise_performance_of_designs(performances: &[Performance], designs: &[Money]) {
+/// The same as `sample_performance_of_design_split`, result for result, but spreading the variants
+/// across `threads` threads. Since each variant's seed only depends on its index, which thread
+/// ends up evaluating which variant makes no difference. Panics if `threads` is 0.
+fn sample_performance_of_design_split_parallel(
+    design_translator: DesignTranslator,
+    environment: Environment,
+    design_variants: &[Money],
+    base_seed: Seed,
+    threads: usize,
+) -> Vec<Performance> {
+    assert!(threads > 0);
+    let mut output = vec![0.; design_variants.len()];
+    let chunk_size = design_variants.len().div_ceil(threads).max(1);
+
+    std::thread::scope(|scope| {
+        for (chunk_index, (variants, outputs)) in design_variants
+            .chunks(chunk_size)
+            .zip(output.chunks_mut(chunk_size))
+            .enumerate()
+        {
+            scope.spawn(move || {
+                for (j, (&m, out)) in variants.iter().zip(outputs.iter_mut()).enumerate() {
+                    let i = chunk_index * chunk_size + j;
+                    *out = performance_of_design_in_environment(
+                        design_translator,
+                        p!(m),
+                        environment.with_seed(xs::split(base_seed, i as u64))
+                    );
+                }
+            });
+        }
+    });
+
+    output
+}
+
+fn visualise_performance_of_designs(performances: &[Performance], designs: &[Money], precision: Precision) {
     assert_eq!(performances.len(), designs.len());
     print!("[");
     for i in 0..performances.len() {
-        print!("({},{}),", designs[i], performances[i]);
+        print!("({},{}),", designs[i], format_performance(performances[i], precision));
     }
     println!("]");
 }
 
+/// The number of local minima in a 1D sweep of performances, so a sweep with more than one
+/// suggests using `minimize_multistart` over a single `minimize`. Runs of equal performances count
+/// as a single point, so a flat-bottomed basin is one minimum, and a flat region sloping down on
+/// one side and up on the other is none. The ends count when they are below their one neighbour,
+/// and a sweep that is entirely flat has one minimum.
+fn count_local_minima(performances: &[Performance]) -> usize {
+    let mut plateaus = performances.to_vec();
+    plateaus.dedup();
+
+    (0..plateaus.len())
+        .filter(|&i| {
+            (i == 0 || plateaus[i - 1] > plateaus[i])
+            && (i + 1 == plateaus.len() || plateaus[i + 1] > plateaus[i])
+        })
+        .count()
+}
+
+/// Reduces `balances` to at most `max_points` points for charting, by splitting them into buckets
+/// and keeping the lowest and highest balance of each, in the order they happened, so no extremes
+/// get lost. Series that already fit are returned as they are. With a `max_points` of 1, there is
+/// no room for both, so each bucket keeps only its lowest.
+fn downsample(balances: &[Money], max_points: usize) -> Vec<Money> {
+    if balances.len() <= max_points {
+        return balances.to_vec()
+    }
+    if max_points == 0 {
+        return Vec::new()
+    }
+
+    let per_bucket = if max_points == 1 { 1 } else { 2 };
+    let bucket_count = max_points / per_bucket;
+    let mut output = Vec::with_capacity(max_points);
+
+    for bucket in 0..bucket_count {
+        let start = bucket * balances.len() / bucket_count;
+        let end = (bucket + 1) * balances.len() / bucket_count;
+        let slice = &balances[start..end];
+
+        let mut min_index = 0;
+        let mut max_index = 0;
+        for i in 0..slice.len() {
+            if slice[i] < slice[min_index] { min_index = i; }
+            if slice[i] > slice[max_index] { max_index = i; }
+        }
+
+        if per_bucket == 1 {
+            output.push(slice[min_index]);
+        } else if min_index <= max_index {
+            output.push(slice[min_index]);
+            output.push(slice[max_index]);
+        } else {
+            output.push(slice[max_index]);
+            output.push(slice[min_index]);
+        }
+    }
+
+    output
+}
+
+/// The balances as a single line of block characters, from lowest to highest. A flat series comes
+/// out as a flat line along the bottom.
+fn sparkline(balances: &[Money]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = balances.iter().copied().min().unwrap_or_default() as i64;
+    let max = balances.iter().copied().max().unwrap_or_default() as i64;
+    // Keep a flat series from dividing by zero.
+    let range = (max - min).max(1);
+
+    balances
+        .iter()
+        .map(|&b| BLOCKS[((b as i64 - min) * (BLOCKS.len() as i64 - 1) / range) as usize])
+        .collect()
+}
+
+#[derive(Clone, Copy)]
 struct FortnightlyRandomWithdrawalArgs {
     seed: Seed,
     high: u32,
+    periods: usize,
 }
 
 impl Default for FortnightlyRandomWithdrawalArgs {
@@ -153,144 +709,2792 @@ impl Default for FortnightlyRandomWithdrawalArgs {
         Self {
             seed: <_>::default(),
             high: 5,
+            periods: ANNUAL_FORTNIGHTS as _,
         }
     }
 }
 
 fn translate_environment_FortnightlyRandomWithdrawal(
-    FortnightlyRandomWithdrawalArgs { seed, high }: FortnightlyRandomWithdrawalArgs
+    FortnightlyRandomWithdrawalArgs { seed, high, periods }: FortnightlyRandomWithdrawalArgs
 ) -> Vec<Transaction> {
     let mut rng = xs::from_seed(seed);
 
-    let mut output = Vec::with_capacity(ANNUAL_FORTNIGHTS as _);
+    let mut output = Vec::with_capacity(periods);
 
-    for _ in 0..ANNUAL_FORTNIGHTS {
+    for _ in 0..periods {
         output.push(t!(w, xs::range(&mut rng, 0..high) as i32));
     }
 
     output
 }
 
-fn translate_FortnightlyDepositAndRandomWithdrawal(design_parameters: DesignParameters) -> Vec<Transaction> {
-    translate_design_FortnightlyDeposit(design_parameters)
-        .into_iter()
-        .zip(translate_environment_FortnightlyRandomWithdrawal(<_>::default()))
-        .flat_map(|(a, b)| {
-            vec![a, b]
-        })
-        .collect::<Vec<_>>()
+/// The expected performance of the design, across the given scenarios, each an environment and
+/// the probability of it being the one that happens. The probabilities are normalized by their
+/// sum, so relative weights work too.
+fn scenario_weighted_performance(
+    design_translator: DesignTranslator,
+    design_parameters: DesignParameters,
+    scenarios: &[(Environment, f32)],
+) -> Performance {
+    let mut weighted_sum = 0.;
+    let mut total_weight = 0.;
+
+    for &(environment, probability) in scenarios {
+        weighted_sum += probability * performance_of_design_in_environment(design_translator, design_parameters, environment);
+        total_weight += probability;
+    }
+
+    weighted_sum / total_weight
 }
 
+/// The constant deposit that minimizes the worst case distance of the final balance from `target`,
+/// when each of `periods` deposits is followed by a withdrawal drawn from `0..high`, as in
+/// `translate_environment_FortnightlyRandomWithdrawal`.
+///
+/// The final balance is `periods * deposit` minus the total withdrawn, and the total withdrawn can
+/// be anything from `0` to `periods * (high - 1)`. So the worst case is always one of those two
+/// extremes, and it is smallest when the target sits halfway between them.
+fn robust_optimal_deposit_uniform(high: u32, periods: usize, target: Money) -> f32 {
+    target as f32 / periods as f32 + high.saturating_sub(1) as f32 / 2.
+}
 
-fn translate_InitialAndFortnightlyDepositAndRandomWithdrawal(design_parameters: DesignParameters) -> Vec<Transaction> {
-        translate_design_InitialAndFortnightlyDeposit(design_parameters)
-        .into_iter()
-        .zip(translate_environment_FortnightlyRandomWithdrawal(<_>::default()))
-        .flat_map(|(a, b)| {
-            vec![a, b]
-        })
-        .collect::<Vec<_>>()
+fn translate_environment_None() -> Vec<Transaction> {
+    vec![]
 }
 
-fn linspace(
-    start: f32,
-    end: f32,
-    num: u16 /* 64k points ought to be enough for anybody! */
-) -> Vec<f32> {
-    let mut output = Vec::with_capacity(num as _);
+#[derive(Clone, Copy)]
+enum Environment {
+    /// Nothing happens to the account except what the design does.
+    None,
+    FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs),
+}
 
-    let delta = (end - start) / num as f32;
+impl Environment {
+    /// The expected amount withdrawn per period.
+    fn mean_withdrawal(self) -> f32 {
+        use Environment::*;
+        match self {
+            None => 0.,
+            // Uniform over `0..high`.
+            FortnightlyRandomWithdrawal(args) => args.high.saturating_sub(1) as f32 / 2.,
+        }
+    }
 
-    for i in 0..num {
-        output.push(start + delta * i as f32);
+    /// The same environment, but running for `periods` periods, if it has a set length.
+    fn with_periods(self, periods: usize) -> Self {
+        use Environment::*;
+        match self {
+            None => None,
+            FortnightlyRandomWithdrawal(args) => FortnightlyRandomWithdrawal(
+                FortnightlyRandomWithdrawalArgs { periods, ..args }
+            ),
+        }
     }
 
-    output
+    /// The same environment, but with its randomness, if any, coming from `seed`.
+    fn with_seed(self, seed: Seed) -> Self {
+        use Environment::*;
+        match self {
+            None => None,
+            FortnightlyRandomWithdrawal(args) => FortnightlyRandomWithdrawal(
+                FortnightlyRandomWithdrawalArgs { seed, ..args }
+            ),
+        }
+    }
 }
 
-type Call = ((f32, f32), Performance);
+fn translate_environment(environment: Environment) -> Vec<Transaction> {
+    use Environment::*;
+    match environment {
+        None => translate_environment_None(),
+        FortnightlyRandomWithdrawal(args) => translate_environment_FortnightlyRandomWithdrawal(args),
+    }
+}
 
-fn sample_performance_of_alternative_design() -> Vec<Call> {
-    let size = 50;
-    let xs1 = linspace(90., 115., size);
-    let xs2 = linspace(0., 6., size);
-    let mut output = Vec::with_capacity(xs2.len());
-
-    for j in 0..size {
-        for i in 0..size {
-            let x1 = xs1[i as usize];
-            let x2 = xs2[i as usize];
-            output.push((
-                (x1, x2),
-                performance_of_design(
-                    translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
-                    p!(x1 as i32, x2 as i32),
-                )
-            ));
+/// Alternate between the design's transactions and the environment's, starting with the design's.
+/// Whatever is left of the longer of the two goes on the end. So `Environment::None` leaves the
+/// design's transactions as they are, and a design with more transactions than the environment
+/// has periods keeps all of them: `translate_design_InitialAndFortnightlyDeposit`'s 27 deposits
+/// against 26 withdrawals give 53 transactions, ending with the 27th deposit, where stopping at
+/// the end of the shorter list would give 52, dropping the last deposit.
+fn interleave(design: Vec<Transaction>, environment: Vec<Transaction>) -> Vec<Transaction> {
+    interleave_tagged(design, environment)
+        .into_iter()
+        .map(Transaction::from)
+        .collect()
+}
+
+/// Like `interleave`, but with each transaction tagged with which of the two it came from.
+fn interleave_tagged(design: Vec<Transaction>, environment: Vec<Transaction>) -> Vec<TaggedTransaction> {
+    let mut output = Vec::with_capacity(design.len() + environment.len());
+
+    let mut design = design.into_iter();
+    let mut environment = environment.into_iter();
+
+    loop {
+        match (design.next(), environment.next()) {
+            (None, None) => break,
+            (a, b) => {
+                output.extend(a.map(|txn| TaggedTransaction { txn, source: Source::Design }));
+                output.extend(b.map(|txn| TaggedTransaction { txn, source: Source::Environment }));
+            }
         }
     }
 
     output
 }
 
-fn visualise_performance_of_alternative_design(calls: Vec<Call>) {
-    print!("[");
-    for i in 0..calls.len() {
-        print!("{:?},", calls[i]);
-    }
-    println!("]");
+/// The net effect on the balance of just the transactions from the given source.
+fn net_flow_from(transactions: &[TaggedTransaction], source: Source) -> Money {
+    transactions
+        .iter()
+        .filter(|t| t.source == source)
+        .fold(0 as Money, |net, t| net.saturating_add(t.txn.signed_amount()))
 }
 
-fn main() {
-    let tx = [t!(d, 10), t!(d, 20), t!(w, 5)];
-    let sb = simulate_balance(&tx);
-    println!("{sb:?}");
+/// Like `simulate_balance`, but with each balance paired with the source of the transaction that
+/// led to it. So there is no entry for the initial balance, and the `i`th entry is the balance
+/// after the `i`th transaction.
+fn simulate_balance_attributed(transactions: &[TaggedTransaction]) -> Vec<(Money, Source)> {
+    let mut account = Account::default();
 
-    println!("{:?}", translate_performance_Target100(&sb));
+    transactions
+        .iter()
+        .map(|&TaggedTransaction { txn, source }| {
+            simulate_transaction(&mut account, txn);
+            (account.balance, source)
+        })
+        .collect()
+}
 
-    println!("{:?}", simulate_balance(&translate_design_FortnightlyDeposit(p!(10,))));
+/// The performance of doing nothing: no design, just `periods` periods of the environment. For
+/// expressing how much better a design is than not bothering.
+fn baseline_performance(environment: Environment, metric: PerformanceTranslator, periods: usize) -> Performance {
+    metric(&simulate_balance(&translate_environment(environment.with_periods(periods))))
+}
 
-    let design_1 = p!(9,);
+/// Like `performance_of_design`, but with the design's transactions interleaved with the given
+/// environment's. `Environment::None` gives the design's performance free of any environment noise,
+/// which is the baseline that the other environments can be measured against.
+fn performance_of_design_in_environment(
+    design_translator: DesignTranslator,
+    design_parameters: DesignParameters,
+    environment: Environment,
+) -> Performance {
+    translate_performance_Target100(
+        &simulate_balance(
+            &interleave(
+                design_translator(design_parameters),
+                translate_environment(environment),
+            )
+        )
+    )
+}
 
-    evaluate!(translate_design_FortnightlyDeposit, design_1);
+/// For each of `highs`, the best constant fortnightly deposit against the default random
+/// withdrawal environment with that `high`, (so with withdrawals drawn from `0..high`,) as found
+/// by `optimizer`, and the `metric` of that deposit. For seeing how the best design shifts as
+/// spending rises. `optimizer` is given the performance as a function of the deposit, and returns
+/// the deposit it thinks is best. The tuples are `(high, deposit, performance)`.
+fn optimal_design_vs_environment(
+    highs: &[u32],
+    optimizer: impl Fn(&dyn Fn(Money) -> Performance) -> Money,
+    metric: PerformanceTranslator,
+) -> Vec<(u32, Money, Performance)> {
+    highs
+        .iter()
+        .map(|&high| {
+            let environment = Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { high, ..<_>::default() });
+            let objective = |deposit: Money| metric(&simulate_balance(&interleave(
+                translate_design_FortnightlyDeposit(p!(deposit)),
+                translate_environment(environment),
+            )));
 
-    println!("{:?}", simulate_balance(&translate_design_FortnightlyDeposit(design_1)));
+            let deposit = optimizer(&objective);
 
-    let design_2 = p!(90, 1);
+            (high, deposit, objective(deposit))
+        })
+        .collect()
+}
 
-    println!("{:?}", simulate_balance(&translate_design_InitialAndFortnightlyDeposit(design_2)));
+/// Ranks the named designs by their performance in `environment`, best first. Designs that perform
+/// exactly the same, as is common under a metric that is flat over a range of designs, are ranked by
+/// name, and then by the total they deposit, least first, with any designs that still tie staying in
+/// the order they were given in. So the ranking only depends on the designs and their order, never
+/// on anything like the platform or the order the performances happened to be computed in.
+fn compare_designs<'name>(
+    designs: &[(&'name str, DesignTranslator, DesignParameters)],
+    environment: Environment,
+) -> Vec<(&'name str, DesignParameters, Performance)> {
+    let mut ranked = designs
+        .iter()
+        .map(|&(name, design_translator, design_parameters)| {
+            let transactions = design_translator(design_parameters);
+            let total_deposited = transactions
+                .iter()
+                .filter(|t| matches!(t.kind, Kind::Deposit))
+                .fold(0 as Money, |total, t| total.saturating_add(t.amount));
+            let performance = performance_of_design_in_environment(design_translator, design_parameters, environment);
+
+            (name, design_parameters, performance, total_deposited)
+        })
+        .collect::<Vec<_>>();
 
-    let design_sweep = (0..16).collect::<Vec<_>>();
-    let performances = sample_performance_of_design(translate_design_FortnightlyDeposit, &design_sweep);
+    // `sort_by` is stable, so this keeps the given order for complete ties.
+    ranked.sort_by(|a, b| {
+        a.2.total_cmp(&b.2)
+            .then_with(|| a.0.cmp(b.0))
+            .then_with(|| a.3.cmp(&b.3))
+    });
 
-    visualise_performance_of_designs(&performances, &design_sweep);
+    ranked
+        .into_iter()
+        .map(|(name, design_parameters, performance, _)| (name, design_parameters, performance))
+        .collect()
+}
 
-    let design_1_minimum_xy = minimize(
-        |[x]| performance_of_design(translate_design_FortnightlyDeposit, p!(x.round() as i32)),
-        regular_simplex_centered_at(100.0, [50.0]),
-        100
-    );
+/// The designs that can be picked by name, such as from the REPL.
+const DESIGNS: [(&str, DesignTranslator); 2] = [
+    ("FortnightlyDeposit", translate_design_FortnightlyDeposit),
+    ("InitialAndFortnightlyDeposit", translate_design_InitialAndFortnightlyDeposit),
+];
 
-    println!(
-        "minimum: {:?} -> {}",
-        design_1_minimum_xy.xs,
-        design_1_minimum_xy.y
-    );
+/// The metrics that can be picked by name, such as from the REPL.
+const METRICS: [(&str, PerformanceTranslator); 1] = [
+    ("Target100", translate_performance_Target100),
+];
 
-    let design_1_minimum = p!(design_1_minimum_xy.xs[0].round() as i32);
+/// Everything needed to run a single experiment, with the design and metric picked by their
+/// names in `DESIGNS` and `METRICS`.
+#[derive(Clone, Copy)]
+struct ExperimentConfig {
+    design: &'static str,
+    design_parameters: DesignParameters,
+    environment: Environment,
+    metric: &'static str,
+}
 
-    let performance_1_minimum = performance_of_design(translate_design_FortnightlyDeposit, design_1_minimum);
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        Self {
+            design: DESIGNS[0].0,
+            design_parameters: p!(9),
+            environment: Environment::None,
+            metric: METRICS[0].0,
+        }
+    }
+}
 
-    println!("performance_1_minimum: {performance_1_minimum:?}");
+/// Rust code that reproduces the experiment's run from scratch, by calling the functions that
+/// `run_experiment` ends up calling, with every setting, the seed included, written out in full.
+/// The code binds `balances` and `performance`, then prints them, so it can be pasted into `main`,
+/// or a test, to reproduce a run for a bug report, without needing the REPL or `ExperimentConfig`.
+fn export_run_as_code(config: &ExperimentConfig) -> String {
+    let environment = match config.environment {
+        Environment::None => "Environment::None".to_string(),
+        Environment::FortnightlyRandomWithdrawal(args) => format!(
+            "Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs {{\n    \
+                seed: {}u128.to_le_bytes(),\n    \
+                high: {},\n    \
+                periods: {},\n\
+            }})",
+            u128::from_le_bytes(args.seed),
+            args.high,
+            args.periods,
+        ),
+    };
 
-    println!("{:?}", simulate_balance(&translate_design_FortnightlyDeposit(design_1_minimum)));
+    format!(
+        "let environment = {environment};\n\
+        let balances = simulate_balance(&interleave(\n    \
+            translate_design_{}({:?}),\n    \
+            translate_environment(environment),\n\
+        ));\n\
+        let performance = translate_performance_{}(&balances);\n\
+        println!(\"{{balances:?}} -> {{performance}}\");\n",
+        config.design,
+        config.design_parameters,
+        config.metric,
+    )
+}
 
-    println!("{:?}", simulate_balance(&translate_environment_FortnightlyRandomWithdrawal(<_>::default())));
+/// The balances from running the experiment, and the performance of those balances, or the first
+/// problem `validate_transactions` finds with the transactions, such as a negative deposit coming
+/// from the design parameters. Panics if the design or metric are not in `DESIGNS` or `METRICS`.
+fn run_experiment(config: &ExperimentConfig) -> Result<(Vec<Money>, Performance), ValidationError> {
+    let design_translator = DESIGNS.iter()
+        .find(|(name, _)| *name == config.design)
+        .expect("the design should be in DESIGNS").1;
+    let metric = METRICS.iter()
+        .find(|(name, _)| *name == config.metric)
+        .expect("the metric should be in METRICS").1;
+
+    let transactions = interleave(
+        design_translator(config.design_parameters),
+        translate_environment(config.environment),
+    );
+    validate_transactions(&transactions)?;
 
-    println!("{:?}", simulate_balance(&translate_FortnightlyDepositAndRandomWithdrawal(design_1)));
+    let balances = simulate_balance(&transactions);
+    let performance = metric(&balances);
 
-    evaluate!(translate_FortnightlyDepositAndRandomWithdrawal, design_1);
+    Ok((balances, performance))
+}
 
-    let calls = sample_performance_of_alternative_design();
-    visualise_performance_of_alternative_design(calls);
+fn translate_FortnightlyDepositAndRandomWithdrawal(design_parameters: DesignParameters) -> Vec<Transaction> {
+    interleave(
+        translate_design_FortnightlyDeposit(design_parameters),
+        translate_environment_FortnightlyRandomWithdrawal(<_>::default()),
+    )
+}
+
+
+fn translate_InitialAndFortnightlyDepositAndRandomWithdrawal(design_parameters: DesignParameters) -> Vec<Transaction> {
+    interleave(
+        translate_design_InitialAndFortnightlyDeposit(design_parameters),
+        translate_environment_FortnightlyRandomWithdrawal(<_>::default()),
+    )
+}
+
+/// The expected final balance, after `periods` periods of depositing `deposit` then having the
+/// environment make its withdrawal. The variance of the environment doesn't come into it, so this
+/// is what the mean of an ensemble of final balances should approach as the ensemble grows.
+fn expected_final_balance(deposit: Money, environment: Environment, periods: usize) -> f32 {
+    periods as f32 * (deposit as f32 - environment.mean_withdrawal())
+}
+
+/// The constant deposit needed each period for the balance to be expected to reach `goal` after
+/// `by_period` periods, when each period's withdrawal is `expected_withdrawal` on average. That is,
+/// `expected_final_balance` solved for the deposit. Since only the expected withdrawal comes into
+/// it, this ignores the variance of the environment entirely: under a random environment the goal
+/// will be missed about as often as it is reached. Panics if `by_period` is 0.
+fn required_deposit_for_goal(goal: Money, by_period: usize, expected_withdrawal: f32) -> f32 {
+    assert!(by_period > 0, "no deposit can reach a goal by period 0");
+
+    goal as f32 / by_period as f32 + expected_withdrawal
+}
+
+/// The largest mean withdrawal per period that a constant `deposit` each period can sustain while
+/// the balance is still expected to reach `goal` after `periods` periods, (a `goal` of 0 for just
+/// not being expected to end up negative.) That is, `required_deposit_for_goal` solved for the
+/// withdrawal: since the required deposit is the goal's share of each period plus the withdrawal,
+/// the withdrawal is whatever is left of `deposit` after that share. Negative when `deposit` isn't
+/// enough to reach `goal` even with no withdrawals at all. Over 0 periods nothing is ever withdrawn,
+/// so there is no limit, and this gives infinity, as long as `goal` isn't positive, and panics
+/// otherwise, since then no withdrawal rate reaches it. As with `required_deposit_for_goal`, only
+/// the expected withdrawal comes into it, so the balance can still fall short in a particular run.
+fn sustainable_withdrawal_rate(deposit: Money, goal: Money, periods: usize) -> f32 {
+    if periods == 0 {
+        assert!(goal <= 0, "no withdrawal rate reaches a positive goal by period 0");
+        return f32::INFINITY
+    }
+
+    deposit as f32 - required_deposit_for_goal(goal, periods, 0.)
+}
+
+/// The number of periods until the design's cumulative deposits first exceed the environment's
+/// cumulative expected withdrawals, treating each of the design's transactions as one period.
+/// `None` if that never happens within the design's periods. This only uses the expected
+/// withdrawals, so a particular run of a random environment may break even sooner or later.
+fn periods_to_break_even(
+    design_translator: DesignTranslator,
+    design_parameters: DesignParameters,
+    environment: Environment,
+) -> Option<usize> {
+    let mean_withdrawal = environment.mean_withdrawal();
+    let mut deposited = 0.;
+
+    for (i, t) in design_translator(design_parameters).into_iter().enumerate() {
+        deposited += t.signed_amount() as f32;
+
+        let periods = i + 1;
+        if deposited > periods as f32 * mean_withdrawal {
+            return Some(periods)
+        }
+    }
+
+    None
+}
+
+/// How many of `env_seeds` make the balance go below zero at some point, when a constant
+/// `deposit` is made at the start of each of `periods` periods, against the default random
+/// withdrawal environment with that seed.
+fn ruined_seed_count(deposit: Money, env_seeds: &[Seed], periods: usize) -> usize {
+    env_seeds
+        .iter()
+        .filter(|&&seed| {
+            simulate_until_ruin(&interleave(
+                vec![t!(d, deposit); periods],
+                translate_environment(Environment::FortnightlyRandomWithdrawal(
+                    FortnightlyRandomWithdrawalArgs { seed, periods, ..<_>::default() }
+                )),
+            )).is_some()
+        })
+        .count()
+}
+
+/// The smallest constant deposit that, made at the start of each of `periods` periods, keeps the
+/// balance from ever going below zero, against the default random withdrawal environment with each
+/// of `env_seeds`. Since a bigger deposit can only make every balance bigger, the deposits that are
+/// safe for every seed form a range with no gaps, so we can bisect to find the bottom of it.
+fn min_safe_deposit(env_seeds: &[Seed], periods: usize) -> Money {
+    let is_safe = |deposit| ruined_seed_count(deposit, env_seeds, periods) == 0;
+
+    // A deposit as big as the biggest possible withdrawal is always safe, since each deposit
+    // comes before that period's withdrawal.
+    let mut low = 0;
+    let mut high = FortnightlyRandomWithdrawalArgs::default().high.saturating_sub(1) as Money;
+
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if is_safe(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+
+    low
+}
+
+/// What happens to the account between one year and the next.
+#[derive(Clone, Copy)]
+struct Rollover {
+    /// Start each year with the previous year's final balance, rather than with an empty account.
+    carry_balance: bool,
+    /// Interest paid on the carried balance between years, as a fraction, so `0.05` is 5%.
+    annual_interest: f32,
+}
+
+/// Runs the design and the environment, interleaved, once per year, for `years` years. Each year's
+/// environment gets its own seed, derived from `seed`. Returns each year's balances one after the
+/// other, with each year's starting balance included, so an interest payment shows up as a jump
+/// between one year's last balance and the next year's first.
+fn simulate_years(
+    design_translator: DesignTranslator,
+    design_parameters: DesignParameters,
+    environment: Environment,
+    years: u16,
+    seed: Seed,
+    Rollover { carry_balance, annual_interest }: Rollover,
+) -> Vec<Money> {
+    let mut rng = xs::from_seed(seed);
+
+    let mut output = Vec::new();
+    let mut balance = Account::default().balance;
+
+    for _ in 0..years {
+        let year = simulate_balance_from(
+            balance,
+            &interleave(
+                design_translator(design_parameters),
+                translate_environment(environment.with_seed(xs::new_seed(&mut rng))),
+            ),
+        );
+
+        balance = if carry_balance {
+            let end = year[year.len() - 1];
+            end.saturating_add((end as f32 * annual_interest).round() as Money)
+        } else {
+            Account::default().balance
+        };
+
+        output.extend(year);
+    }
+
+    output
+}
+
+/// An endless stream of distinct environment seeds, the same ones in the same order every time.
+/// Everything that runs over an ensemble of seeds draws them from here, so they all agree on what
+/// the ensemble is.
+fn ensemble_seed_stream() -> impl Iterator<Item = Seed> {
+    let mut rng = xs::from_seed(<_>::default());
+
+    std::iter::repeat_with(move || xs::new_seed(&mut rng))
+}
+
+/// The first `count` seeds of `ensemble_seed_stream`.
+fn ensemble_seeds(count: usize) -> Vec<Seed> {
+    ensemble_seed_stream().take(count).collect()
+}
+
+/// The number of environment seeds needed for the mean performance of the design, against the
+/// random withdrawal environment, to have a standard error of at most `target_stderr`. This runs
+/// batches of seeds from `ensemble_seed_stream`, (so the same ones `ensemble_seeds` gives, in the
+/// same order,) starting with a small pilot batch, and growing it until the batch is at least as
+/// big as what the spread seen so far says is needed. So the estimate of the spread comes from at
+/// least as many seeds as are recommended. Never recommends fewer than 2, since a standard error needs two values.
+fn recommend_seed_count(
+    design_translator: DesignTranslator,
+    design_parameters: DesignParameters,
+    target_stderr: Performance,
+) -> usize {
+    const PILOT: usize = 16;
+    const MAX: usize = 1 << 16;
+
+    let mut seeds = ensemble_seed_stream();
+    let mut performances = Vec::with_capacity(PILOT);
+    let mut batch = PILOT;
+
+    loop {
+        while performances.len() < batch {
+            let seed = seeds.next().expect("the stream never ends");
+            let environment = Environment::FortnightlyRandomWithdrawal(
+                FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() }
+            );
+            performances.push(performance_of_design_in_environment(design_translator, design_parameters, environment));
+        }
+
+        let standard_deviation = stats::sample_variance(&performances).sqrt();
+        let needed = ((standard_deviation / target_stderr).powi(2).ceil() as usize).clamp(2, MAX);
+
+        if needed <= performances.len() || performances.len() >= MAX {
+            return needed
+        }
+
+        batch = needed;
+    }
+}
+
+/// The mean of the worst `alpha` fraction of `performances`, such as those of a design over a seed
+/// ensemble, also known as the conditional value at risk. Since a lower performance is better, the
+/// worst are the highest. Where the worst case only looks at the single worst seed, this averages
+/// over the whole tail, so it is less at the mercy of one unlucky seed, while still ranking designs
+/// by how badly they do when things go wrong rather than on average. The tail is rounded up to a
+/// whole number of performances, so it always has at least one. Panics if `performances` is empty,
+/// or `alpha` is not in `(0, 1]`, since no tail at all has no mean.
+fn expected_shortfall(performances: &[Performance], alpha: f32) -> Performance {
+    assert!(!performances.is_empty());
+    assert!(alpha > 0. && alpha <= 1.);
+
+    let mut sorted = performances.to_vec();
+    sorted.sort_by(|a, b| b.partial_cmp(a).expect("should have no NaNs"));
+
+    let tail = ((alpha * sorted.len() as f32).ceil() as usize).clamp(1, sorted.len());
+
+    stats::mean(&sorted[..tail])
+}
+
+/// How consistent `performances`, such as those of a design over a seed ensemble, are: their
+/// sample standard deviation divided by their mean, (the coefficient of variation, or the
+/// reciprocal of the signal-to-noise ratio.) So as with the performances themselves, lower is
+/// better, with 0 meaning every seed gave the same performance. Dividing by the mean makes the
+/// score comparable between designs with very different performances. All equal performances give
+/// 0, even if they are all 0, while a mean of 0 with any spread gives infinity. Panics if there
+/// are fewer than two performances.
+fn robustness_score(performances: &[Performance]) -> f32 {
+    assert!(performances.len() >= 2);
+
+    let standard_deviation = stats::sample_variance(performances).sqrt();
+    if standard_deviation == 0. {
+        return 0.
+    }
+
+    standard_deviation / stats::mean(performances).abs()
+}
+
+/// The two parts of an ANOVA-style split of the spread in performance.
+#[derive(Clone, Copy, Debug)]
+struct VarianceComponents {
+    /// The variance of the per-design mean performances. That is, the part due to the design.
+    between_design: Performance,
+    /// The mean of the per-design variances across seeds. That is, the part due to the environment.
+    within_design: Performance,
+}
+
+/// Evaluates every fortnightly deposit in `design_variants` against the random withdrawal
+/// environment for every seed in `seeds`, and splits the variance of the resulting performances.
+/// Since every design sees the same number of seeds, the two components sum to the variance of
+/// all the performances taken together.
+fn variance_decomposition(
+    design_variants: &[Money],
+    seeds: &[Seed],
+    metric: PerformanceTranslator,
+) -> VarianceComponents {
+    let mut design_means = Vec::with_capacity(design_variants.len());
+    let mut design_variances = Vec::with_capacity(design_variants.len());
+
+    for &m in design_variants {
+        let performances = seeds
+            .iter()
+            .map(|&seed| {
+                metric(&simulate_balance(&interleave(
+                    translate_design_FortnightlyDeposit(p!(m)),
+                    translate_environment_FortnightlyRandomWithdrawal(
+                        FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() }
+                    ),
+                )))
+            })
+            .collect::<Vec<_>>();
+
+        design_means.push(stats::mean(&performances));
+        design_variances.push(stats::variance(&performances));
+    }
+
+    VarianceComponents {
+        between_design: stats::variance(&design_means),
+        within_design: stats::mean(&design_variances),
+    }
+}
+
+fn linspace(
+    start: f32,
+    end: f32,
+    num: u16 /* 64k points ought to be enough for anybody! */
+) -> Vec<f32> {
+    let mut output = Vec::with_capacity(num as _);
+
+    let delta = (end - start) / num as f32;
+
+    for i in 0..num {
+        output.push(start + delta * i as f32);
+    }
+
+    output
+}
+
+/// The ranges of the two design parameters to explore.
+#[derive(Clone, Copy)]
+struct DesignSpace {
+    x1: (f32, f32),
+    x2: (f32, f32),
+}
+
+const ALTERNATIVE_DESIGN_SPACE: DesignSpace = DesignSpace {
+    x1: (90., 115.),
+    x2: (0., 6.),
+};
+
+/// The radical inverse of `index` in the given base. That is, the digits of `index` in that base,
+/// mirrored around the point, so 6 in base 2 is 110, which becomes 0.011, or 3/8.
+fn radical_inverse(mut index: u32, base: u32) -> f32 {
+    let mut output = 0.;
+    let mut place = 1. / base as f32;
+
+    while index > 0 {
+        output += (index % base) as f32 * place;
+        index /= base;
+        place /= base as f32;
+    }
+
+    output
+}
+
+/// `n` starting points spread evenly over the space, from the 2D Halton sequence with bases 2 and
+/// 3. Unlike independent random points, these don't clump, and each new point lands in the biggest
+/// gaps the previous ones left. The sequence starts from index 1 since index 0 is always the corner.
+fn halton_starts(space: DesignSpace, n: u32) -> Vec<[f32; 2]> {
+    (1..=n)
+        .map(|i| [
+            space.x1.0 + radical_inverse(i, 2) * (space.x1.1 - space.x1.0),
+            space.x2.0 + radical_inverse(i, 3) * (space.x2.1 - space.x2.0),
+        ])
+        .collect()
+}
+
+/// `n` independent, uniformly random starting points in the space.
+fn random_starts(space: DesignSpace, n: u32, seed: Seed) -> Vec<[f32; 2]> {
+    let mut rng = xs::from_seed(seed);
+
+    (0..n)
+        .map(|_| [
+            space.x1.0 + xs::zero_to_one(&mut rng) * (space.x1.1 - space.x1.0),
+            space.x2.0 + xs::zero_to_one(&mut rng) * (space.x2.1 - space.x2.0),
+        ])
+        .collect()
+}
+
+type Call = ((f32, f32), Performance);
+
+fn sample_performance_of_alternative_design() -> Vec<Call> {
+    let mut output = Vec::new();
+
+    sweep_into(
+        &mut output,
+        translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+        ALTERNATIVE_DESIGN_SPACE,
+        50
+    );
+
+    output
+}
+
+/// Clears `buf`, then fills it with the performance of the design at each point of a
+/// `resolution` by `resolution` grid over `space`, `x1` varying fastest. Reusing the same `buf`
+/// across sweeps avoids allocating once its capacity is large enough, for when sweeps are
+/// repeated, say as a parameter is adjusted interactively.
+fn sweep_into(
+    buf: &mut Vec<Call>,
+    design_translator: DesignTranslator,
+    space: DesignSpace,
+    resolution: u16,
+) {
+    let xs1 = linspace(space.x1.0, space.x1.1, resolution);
+    let xs2 = linspace(space.x2.0, space.x2.1, resolution);
+
+    buf.clear();
+    buf.reserve(xs1.len() * xs2.len());
+
+    for &x2 in &xs2 {
+        for &x1 in &xs1 {
+            buf.push((
+                (x1, x2),
+                performance_of_design(design_translator, p!(x1 as i32, x2 as i32)),
+            ));
+        }
+    }
+}
+
+/// Like `sample_performance_of_alternative_design`, but for any design and space, and rather than
+/// a uniform grid, starts with a coarse `initial_res` by `initial_res` grid of cells, then
+/// repeatedly splits the cell whose corners differ the most in performance into four, until
+/// `refine_budget` more evaluations have been spent. So the evaluations end up concentrated where
+/// the performance changes the most, such as near the minimum, instead of being spent on flat
+/// regions. Returns every evaluated point once, in the order they were evaluated.
+fn adaptive_sweep(
+    design_translator: DesignTranslator,
+    space: DesignSpace,
+    initial_res: u16,
+    refine_budget: usize,
+) -> Vec<Call> {
+    use std::collections::HashMap;
+
+    // Splitting a cell takes at most its center and the middles of its four edges.
+    const POINTS_PER_SPLIT: usize = 5;
+
+    struct Cell {
+        x1: (f32, f32),
+        x2: (f32, f32),
+        variation: Performance,
+    }
+
+    let mut output: Vec<Call> = Vec::new();
+    let mut seen: HashMap<(u32, u32), Performance> = HashMap::new();
+
+    let mut evaluate = |x1: f32, x2: f32, output: &mut Vec<Call>| {
+        *seen.entry((x1.to_bits(), x2.to_bits())).or_insert_with(|| {
+            let performance = performance_of_design(design_translator, p!(x1 as i32, x2 as i32));
+            output.push(((x1, x2), performance));
+            performance
+        })
+    };
+
+    let mut cell = |x1: (f32, f32), x2: (f32, f32), output: &mut Vec<Call>| {
+        let corners = [
+            evaluate(x1.0, x2.0, output),
+            evaluate(x1.1, x2.0, output),
+            evaluate(x1.0, x2.1, output),
+            evaluate(x1.1, x2.1, output),
+        ];
+        let min = corners.iter().copied().fold(Performance::INFINITY, Performance::min);
+        let max = corners.iter().copied().fold(Performance::NEG_INFINITY, Performance::max);
+
+        Cell { x1, x2, variation: max - min }
+    };
+
+    let res = initial_res.max(1) as usize;
+    let step1 = (space.x1.1 - space.x1.0) / res as f32;
+    let step2 = (space.x2.1 - space.x2.0) / res as f32;
+
+    let mut cells = Vec::with_capacity(res * res);
+    for j in 0..res {
+        for i in 0..res {
+            let x1 = (space.x1.0 + step1 * i as f32, space.x1.0 + step1 * (i + 1) as f32);
+            let x2 = (space.x2.0 + step2 * j as f32, space.x2.0 + step2 * (j + 1) as f32);
+            cells.push(cell(x1, x2, &mut output));
+        }
+    }
+
+    // Charge every split its full cost, even when some of its points were already evaluated, so
+    // that cells too small to split any further can't make this loop forever.
+    let mut spent = 0;
+
+    while spent + POINTS_PER_SPLIT <= refine_budget {
+        spent += POINTS_PER_SPLIT;
+
+        let Some((index, _)) = cells
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.variation > 0.)
+            .max_by(|(_, a), (_, b)| a.variation.partial_cmp(&b.variation).expect("should have no NaNs"))
+        else {
+            // Everything left is flat, so splitting further would show nothing new.
+            break
+        };
+
+        let Cell { x1, x2, .. } = cells.swap_remove(index);
+        let middle1 = (x1.0 + x1.1) / 2.;
+        let middle2 = (x2.0 + x2.1) / 2.;
+
+        for (x1, x2) in [
+            ((x1.0, middle1), (x2.0, middle2)),
+            ((middle1, x1.1), (x2.0, middle2)),
+            ((x1.0, middle1), (middle2, x2.1)),
+            ((middle1, x1.1), (middle2, x2.1)),
+        ] {
+            cells.push(cell(x1, x2, &mut output));
+        }
+    }
+
+    output
+}
+
+/// At each point of a `resolution` by `resolution` grid over the space, the gradient of the
+/// performance, as `(point, gradient)` pairs. The gradient points uphill, so the negation of it is
+/// the direction that improves the design fastest. The gradients are central differences of
+/// `performance_of_design_smooth`, one whole amount wide, since `performance_of_design` itself is
+/// flat between whole amounts, which would make any narrower difference zero almost everywhere.
+fn performance_gradient_field(
+    design_translator: DesignTranslator,
+    space: DesignSpace,
+    resolution: u16,
+) -> Vec<((f32, f32), (f32, f32))> {
+    const H: f32 = 0.5;
+
+    let xs1 = linspace(space.x1.0, space.x1.1, resolution);
+    let xs2 = linspace(space.x2.0, space.x2.1, resolution);
+    let mut output = Vec::with_capacity(xs1.len() * xs2.len());
+
+    let performance = |x1, x2| performance_of_design_smooth(design_translator, (x1, x2));
+
+    for &x2 in &xs2 {
+        for &x1 in &xs1 {
+            output.push((
+                (x1, x2),
+                (
+                    (performance(x1 + H, x2) - performance(x1 - H, x2)) / (2. * H),
+                    (performance(x1, x2 + H) - performance(x1, x2 - H)) / (2. * H),
+                ),
+            ));
+        }
+    }
+
+    output
+}
+
+fn visualise_performance_of_alternative_design(calls: Vec<Call>, precision: Precision) {
+    print!("[");
+    for ((x1, x2), performance) in calls {
+        print!("(({x1:?}, {x2:?}), {}),", format_performance(performance, precision));
+    }
+    println!("]");
+}
+
+fn main() {
+    if std::env::args().skip(1).any(|arg| arg == "--repl") {
+        repl::run(std::io::stdin().lock(), std::io::stdout()).expect("stdin and stdout should work");
+        return
+    }
+
+    let tx = [t!(d, 10), t!(d, 20), t!(w, 5)];
+    let sb = simulate_balance(&tx);
+    println!("{sb:?}");
+
+    println!("{:?}", translate_performance_Target100(&sb));
+
+    for transactions in [&tx[..], &[t!(d, 10), t!(w, -5)]] {
+        if let Err(error) = validate_transactions(transactions) {
+            println!("invalid transactions: {error}");
+        }
+    }
+
+    let tx_with_no_op = [t!(d, 10), t!(n), t!(w, 5)];
+    println!("{:?} from {} transactions", simulate_balance(&tx_with_no_op), count_transactions(&tx_with_no_op));
+
+    println!("{:?}", simulate_balance(&translate_design_FortnightlyDeposit(p!(10,))));
+
+    let design_1 = p!(9,);
+
+    evaluate!(translate_design_FortnightlyDeposit, design_1);
+
+    println!("{:?}", simulate_balance(&translate_design_FortnightlyDeposit(design_1)));
+
+    let design_2 = p!(90, 1);
+
+    for (rank, (name, design, performance)) in compare_designs(
+        &[
+            ("design_1", translate_design_FortnightlyDeposit, design_1),
+            ("design_2", translate_design_InitialAndFortnightlyDeposit, design_2),
+        ],
+        Environment::FortnightlyRandomWithdrawal(<_>::default()),
+    ).into_iter().enumerate() {
+        println!("{}. {name} {design:?}: {}", rank + 1, format_performance(performance, PRECISION));
+    }
+
+    for (name, design_translator, design) in [
+        ("design_1", translate_design_FortnightlyDeposit as DesignTranslator, design_1),
+        ("design_2", translate_design_InitialAndFortnightlyDeposit, design_2),
+    ] {
+        println!(
+            "{name} at 0.5 per transaction: {}",
+            format_performance(performance_of_design_penalized(design_translator, design, translate_performance_Target100, 0.5), PRECISION)
+        );
+    }
+
+    println!("{:?}", simulate_balance(&translate_design_InitialAndFortnightlyDeposit(design_2)));
+
+    let design_sweep = (0..16).collect::<Vec<_>>();
+    let performances = sample_performance_of_design(translate_design_FortnightlyDeposit, Environment::None, &design_sweep);
+
+    visualise_performance_of_designs(&performances, &design_sweep, Precision::Full);
+
+    let performances_with_withdrawals = sample_performance_of_design(
+        translate_design_FortnightlyDeposit,
+        Environment::FortnightlyRandomWithdrawal(<_>::default()),
+        &design_sweep,
+    );
+
+    visualise_performance_of_designs(&performances_with_withdrawals, &design_sweep, Precision::Full);
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let split_performances = sample_performance_of_design_split_parallel(
+        translate_design_FortnightlyDeposit,
+        Environment::FortnightlyRandomWithdrawal(<_>::default()),
+        &design_sweep,
+        <_>::default(),
+        threads,
+    );
+    let matches_serial = split_performances == sample_performance_of_design_split(
+        translate_design_FortnightlyDeposit,
+        Environment::FortnightlyRandomWithdrawal(<_>::default()),
+        &design_sweep,
+        <_>::default(),
+    );
+    println!("with an independent environment for each design, on {threads} threads (same as serial: {matches_serial}):");
+    visualise_performance_of_designs(&split_performances, &design_sweep, Precision::Full);
+
+    for (name, sweep) in [("without", &performances), ("with", &performances_with_withdrawals)] {
+        let minima = count_local_minima(sweep);
+        if minima > 1 {
+            println!("warning: the sweep {name} withdrawals has {minima} local minima, so prefer multiple starts");
+        }
+    }
+
+    let design_1_minimum_xy = minimize(
+        |[x]| performance_of_design(translate_design_FortnightlyDeposit, p!(x.round() as i32)),
+        regular_simplex_centered_at(100.0, [50.0]),
+        100
+    );
+
+    println!(
+        "minimum: {:?} -> {}",
+        design_1_minimum_xy.xs,
+        format_performance(design_1_minimum_xy.y, PRECISION)
+    );
+
+    let design_1_smooth_minimum = minimize(
+        |[x]| performance_of_design_smooth(translate_design_FortnightlyDeposit, (x, 0.)),
+        regular_simplex_centered_at(100.0, [50.0]),
+        100
+    );
+
+    println!(
+        "smooth minimum: {:?} -> {}, snapped to {:?}",
+        design_1_smooth_minimum.xs,
+        format_performance(design_1_smooth_minimum.y, PRECISION),
+        best_whole_design_near(translate_design_FortnightlyDeposit, (design_1_smooth_minimum.xs[0], 0.))
+    );
+
+    for design in [p!(2), design_1, p!(6)] {
+        println!(
+            "one more dollar a fortnight at {design:?} improves the performance by {}",
+            format_performance(marginal_performance_per_dollar(translate_design_FortnightlyDeposit, design, 0, translate_performance_Target100), PRECISION)
+        );
+    }
+
+    let (_, design_1_probes) = minimize_recording(
+        |[x]| performance_of_design(translate_design_FortnightlyDeposit, p!(x.round() as i32)),
+        regular_simplex_centered_at(100.0, [50.0]),
+        100
+    );
+
+    print!("probes: [");
+    for probe in design_1_probes.iter().take(20) {
+        print!("({},{}),", probe.xs[0], format_performance(probe.y, PRECISION));
+    }
+    println!("...]");
+
+    let design_1_minimum = p!(design_1_minimum_xy.xs[0].round() as i32);
+
+    let performance_1_minimum = performance_of_design(translate_design_FortnightlyDeposit, design_1_minimum);
+
+    println!("performance_1_minimum: {performance_1_minimum:?}");
+
+    println!("{:?}", simulate_balance(&translate_design_FortnightlyDeposit(design_1_minimum)));
+
+    println!("{:?}", simulate_balance(&translate_environment_FortnightlyRandomWithdrawal(<_>::default())));
+
+    println!("{:?}", simulate_balance(&translate_FortnightlyDepositAndRandomWithdrawal(design_1)));
+    println!("{}", sparkline(&simulate_balance(&translate_FortnightlyDepositAndRandomWithdrawal(design_1))));
+    println!(
+        "{:?}",
+        simulate_balance(&resample_transactions(&translate_FortnightlyDepositAndRandomWithdrawal(design_1), 2))
+    );
+    print!(
+        "{}",
+        export_run_as_code(&ExperimentConfig {
+            environment: Environment::FortnightlyRandomWithdrawal(<_>::default()),
+            ..<_>::default()
+        })
+    );
+
+    let design_1_tagged = interleave_tagged(
+        translate_design_FortnightlyDeposit(design_1),
+        translate_environment(Environment::FortnightlyRandomWithdrawal(<_>::default())),
+    );
+    println!(
+        "planned: {}, actual: {}, final balance: {:?}",
+        net_flow_from(&design_1_tagged, Source::Design),
+        net_flow_from(&design_1_tagged, Source::Design) + net_flow_from(&design_1_tagged, Source::Environment),
+        simulate_balance(&design_1_tagged).last()
+    );
+
+    let (_, design_1_violations) = simulate_balance_constrained(&design_1_tagged, 10, 150);
+    println!(
+        "design_1 kept between 10 and 150: {design_1_violations:?}, a penalty of {}",
+        format_performance(translate_performance_ViolationPenalty(design_1_violations, 1., 0.1), PRECISION)
+    );
+
+    let mut previous = 0;
+    let mut movement_by_design = 0;
+    let mut movement = 0;
+    for (balance, source) in simulate_balance_attributed(&design_1_tagged) {
+        let change = (balance - previous).abs();
+        movement += change;
+        if source == Source::Design {
+            movement_by_design += change;
+        }
+        previous = balance;
+    }
+    println!("{movement_by_design} of {movement} of design_1's balance movement was planned");
+
+    println!(
+        "autocorrelation: {:?}",
+        autocorrelation(&simulate_balance(&translate_FortnightlyDepositAndRandomWithdrawal(design_1)), 4)
+    );
+    println!(
+        "lowest balance: {}",
+        fold_balance(&translate_FortnightlyDepositAndRandomWithdrawal(design_1), Money::MAX, Money::min)
+    );
+    println!(
+        "expected final balance: {}",
+        expected_final_balance(design_1.0, Environment::FortnightlyRandomWithdrawal(<_>::default()), ANNUAL_FORTNIGHTS as _)
+    );
+
+    evaluate!(translate_FortnightlyDepositAndRandomWithdrawal, design_1);
+
+    evaluate!(translate_FortnightlyDepositAndRandomWithdrawal, design_1, Precision::Decimals(4));
+
+    let design_1_baseline = performance_of_design_in_environment(translate_design_FortnightlyDeposit, design_1, Environment::None);
+    let design_1_in_random = performance_of_design_in_environment(
+        translate_design_FortnightlyDeposit,
+        design_1,
+        Environment::FortnightlyRandomWithdrawal(<_>::default()),
+    );
+
+    println!(
+        "design_1 without environment: {}, with random withdrawals: {}",
+        format_performance(design_1_baseline, PRECISION),
+        format_performance(design_1_in_random, PRECISION)
+    );
+
+    let do_nothing = baseline_performance(
+        Environment::FortnightlyRandomWithdrawal(<_>::default()),
+        translate_performance_Target100,
+        ANNUAL_FORTNIGHTS as _,
+    );
+    println!(
+        "doing nothing: {}, so design_1 improves on it by {}",
+        format_performance(do_nothing, PRECISION),
+        format_performance(do_nothing - design_1_in_random, PRECISION)
+    );
+
+    let normal_year = Environment::FortnightlyRandomWithdrawal(<_>::default());
+    let recession = Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { high: 15, ..<_>::default() });
+
+    println!(
+        "design_1 expected over a normal year (0.8) or a recession (0.2): {}",
+        format_performance(
+            scenario_weighted_performance(translate_design_FortnightlyDeposit, design_1, &[(normal_year, 0.8), (recession, 0.2)]),
+            PRECISION
+        )
+    );
+    println!(
+        "    from an effective sample size of {:.2} scenarios",
+        stats::effective_sample_size(&[0.8, 0.2])
+    );
+
+    println!(
+        "robust optimal deposit for a final balance of 100: {}",
+        robust_optimal_deposit_uniform(FortnightlyRandomWithdrawalArgs::default().high, ANNUAL_FORTNIGHTS as _, 100)
+    );
+
+    let alternative_minimum = minimize(
+        |[a, b]| performance_of_design(
+            translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+            p!(a.round() as i32, b.round() as i32)
+        ),
+        axis_simplex_centered_at([100., 3.], [10., 2.]),
+        100
+    );
+
+    println!(
+        "alternative design minimum: {:?} -> {}",
+        alternative_minimum.xs,
+        format_performance(alternative_minimum.y, PRECISION)
+    );
+
+    let alternative_minimum_on_lattice = minimize_on_lattice(
+        |[a, b]| performance_of_design(
+            translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+            p!(a as i32, b as i32)
+        ),
+        axis_simplex_centered_at([100., 3.], [10., 2.]),
+        100
+    );
+
+    println!(
+        "alternative design minimum on the integer lattice: {:?} -> {}",
+        alternative_minimum_on_lattice.xs,
+        format_performance(alternative_minimum_on_lattice.y, PRECISION)
+    );
+
+    let alternative_minimized = minimize_with_simplex(
+        |[a, b]| performance_of_design(
+            translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+            p!(a.round() as i32, b.round() as i32)
+        ),
+        axis_simplex_centered_at([100., 3.], [10., 2.]),
+        100
+    );
+
+    println!("{}", alternative_minimized.to_json());
+
+    // Avoiding ruin comes strictly first; only among equally safe deposits does the cost matter.
+    let ruin_seeds = ensemble_seeds(16);
+    let safe_and_cheap = minimize_by(
+        |[deposit]| {
+            let deposit = deposit.round() as Money;
+            let ruined = ruined_seed_count(deposit, &ruin_seeds, ANNUAL_FORTNIGHTS as _);
+
+            [ruined as f32, deposit as f32]
+        },
+        lexicographic,
+        regular_simplex_centered_at(8., [4.]),
+        100
+    );
+
+    println!(
+        "lexicographic minimum: deposit {} ruins {} of {} seeds",
+        safe_and_cheap.best.y[1],
+        safe_and_cheap.best.y[0],
+        ruin_seeds.len(),
+    );
+
+    let alternative_minimum_scaled = minimize_scaled(
+        |[a, b]| performance_of_design(
+            translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+            p!(a.round() as i32, b.round() as i32)
+        ),
+        [100., 3.],
+        [25., 6.],
+        100
+    );
+
+    println!(
+        "alternative design minimum, scaled: {:?} -> {}",
+        alternative_minimum_scaled.xs,
+        format_performance(alternative_minimum_scaled.y, PRECISION)
+    );
+
+    let alternative_objective = |[a, b]: [f32; 2]| performance_of_design(
+        translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+        p!(a.round() as i32, b.round() as i32)
+    );
+
+    for (name, starts) in [
+        ("random", random_starts(ALTERNATIVE_DESIGN_SPACE, 8, <_>::default())),
+        ("halton", halton_starts(ALTERNATIVE_DESIGN_SPACE, 8)),
+    ] {
+        if let Some(best) = minimize_multistart(alternative_objective, &starts, 3.0, 100) {
+            println!("alternative design minimum from {name} starts: {:?} -> {}", best.xs, format_performance(best.y, PRECISION));
+        }
+    }
+
+    let alternative_rounded = |[a, b]: [f32; 2]| performance_of_design(
+        translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+        p!(a.round() as i32, b.round() as i32)
+    );
+    let alternative_smooth = |[a, b]: [f32; 2]| performance_of_design_smooth(
+        translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+        (a, b)
+    );
+    let benchmark_report = benchmark::benchmark_optimizers(
+        &[
+            benchmark::Objective { name: "alternative, rounded", f: &alternative_rounded, space: ALTERNATIVE_DESIGN_SPACE },
+            benchmark::Objective { name: "alternative, smooth", f: &alternative_smooth, space: ALTERNATIVE_DESIGN_SPACE },
+        ],
+        &[benchmark::NELDER_MEAD, benchmark::RANDOM_SEARCH],
+        200,
+    );
+    println!("{benchmark_report}");
+    if let Some(winner) = benchmark_report.most_wins() {
+        println!("best optimizer with 200 evaluations: {winner}");
+    }
+
+    for (high, deposit, performance) in optimal_design_vs_environment(
+        &[1, 5, 10, 20, 40],
+        |objective| minimize_on_lattice(
+            |[deposit]| objective(deposit as Money),
+            regular_simplex_centered_at(16., [8.]),
+            100
+        ).xs[0] as Money,
+        translate_performance_Target100,
+    ) {
+        println!("withdrawing from 0..{high}, the best deposit is {deposit} -> {}", format_performance(performance, PRECISION));
+    }
+
+    let mut calls = sample_performance_of_alternative_design();
+    let best_with_withdrawals = calls.iter().map(|&(_, performance)| performance).fold(f32::INFINITY, f32::min);
+    // Kept for the visualisation below, since `sweep_into` overwrites `calls`.
+    let calls_with_withdrawals = calls.clone();
+    sweep_into(&mut calls, translate_design_InitialAndFortnightlyDeposit, ALTERNATIVE_DESIGN_SPACE, 50);
+    let best_without_withdrawals = calls.iter().map(|&(_, performance)| performance).fold(f32::INFINITY, f32::min);
+    println!(
+        "best swept alternative design: {} with withdrawals, {} without",
+        format_performance(best_with_withdrawals, PRECISION),
+        format_performance(best_without_withdrawals, PRECISION)
+    );
+
+    visualise_performance_of_alternative_design(calls_with_withdrawals, Precision::Full);
+
+    visualise_performance_of_alternative_design(
+        adaptive_sweep(translate_InitialAndFortnightlyDepositAndRandomWithdrawal, ALTERNATIVE_DESIGN_SPACE, 8, 400),
+        Precision::Full
+    );
+
+    print!("[");
+    for ((x1, x2), (dx1, dx2)) in performance_gradient_field(
+        translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+        ALTERNATIVE_DESIGN_SPACE,
+        5
+    ) {
+        print!("(({x1:?}, {x2:?}), ({dx1:?}, {dx2:?})),");
+    }
+    println!("]");
+
+    println!(
+        "design_2 breaks even with random withdrawals after {:?} periods",
+        periods_to_break_even(
+            translate_design_InitialAndFortnightlyDeposit,
+            design_2,
+            Environment::FortnightlyRandomWithdrawal(<_>::default()),
+        )
+    );
+
+    let design_1_spec = [
+        spec::Constraint::FinalBalanceAtLeast(150),
+        spec::Constraint::NeverBelow(0),
+        spec::Constraint::WithinOfTargetAfter { target: 100, tolerance: 50, after_transactions: 20 },
+    ];
+    let design_1_report = spec::check_spec(translate_design_FortnightlyDeposit, design_1, <_>::default(), &design_1_spec);
+
+    for (constraint, passed) in &design_1_report.results {
+        println!("{constraint:?}: {}", if *passed { "pass" } else { "fail" });
+    }
+    println!("design_1 {} its spec", if design_1_report.passed() { "meets" } else { "does not meet" });
+
+    println!(
+        "smallest deposit that never goes below zero over 32 seeds: {}",
+        min_safe_deposit(&ensemble_seeds(32), ANNUAL_FORTNIGHTS as _)
+    );
+
+    let decade = simulate_years(
+        translate_design_FortnightlyDeposit,
+        design_1,
+        Environment::FortnightlyRandomWithdrawal(<_>::default()),
+        10,
+        <_>::default(),
+        Rollover { carry_balance: true, annual_interest: 0.05 },
+    );
+
+    println!("balance after a decade of design_1 at 5% interest: {}", decade[decade.len() - 1]);
+    println!("{}", sparkline(&downsample(&decade, 80)));
+
+    let mut century_median = stats::PSquare::new(0.5);
+    let century = simulate_balance_streaming(
+        (0..100).flat_map(|_| translate_FortnightlyDepositAndRandomWithdrawal(design_1)),
+        10_000,
+        |balance| century_median.observe(balance as f32),
+    );
+    if let Some(estimate) = century_median.estimate() {
+        let exact = stats::median(
+            &simulate_balance(&(0..100).flat_map(|_| translate_FortnightlyDepositAndRandomWithdrawal(design_1)).collect::<Vec<_>>())
+                .into_iter()
+                .map(|balance| balance as f32)
+                .collect::<Vec<_>>()
+        );
+        println!("median balance over the century: about {estimate}, exactly {exact}");
+    }
+
+    match century {
+        Ok(balance) => println!("balance after a century of design_1 without interest: {balance}"),
+        Err(TransactionError::CapExceeded { cap }) => println!("a century of design_1 is more than {cap} transactions"),
+    }
+
+    println!(
+        "seeds needed for a standard error of 0.1 on design_1: {}",
+        recommend_seed_count(translate_design_FortnightlyDeposit, design_1, 0.1)
+    );
+
+    println!(
+        "deposit needed each fortnight to expect 5000 by fortnight 26: {}",
+        required_deposit_for_goal(5000, ANNUAL_FORTNIGHTS as _, Environment::FortnightlyRandomWithdrawal(<_>::default()).mean_withdrawal())
+    );
+
+    println!(
+        "mean withdrawal design_1 can sustain over a year: {}",
+        sustainable_withdrawal_rate(design_1.0, 0, ANNUAL_FORTNIGHTS as _)
+    );
+
+    let design_1_performances = ensemble_seeds(32).into_iter().map(|seed|
+        performance_of_design_in_environment(
+            translate_design_FortnightlyDeposit,
+            design_1,
+            Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() }),
+        )
+    ).collect::<Vec<_>>();
+    let (low, high) = stats::bootstrap_ci(&design_1_performances, 1000, 0.95, <_>::default());
+
+    println!(
+        "design_1 over 32 seeds: {}, with a 95% bootstrap interval of {} to {}",
+        format_performance(stats::mean(&design_1_performances), PRECISION),
+        format_performance(low, PRECISION),
+        format_performance(high, PRECISION),
+    );
+
+    println!(
+        "design_1's expected shortfall over the worst 10% of seeds: {}",
+        format_performance(expected_shortfall(&design_1_performances, 0.1), PRECISION)
+    );
+
+    println!("design_1's robustness score: {}", format_performance(robustness_score(&design_1_performances), PRECISION));
+
+    let components = variance_decomposition(&design_sweep, &ensemble_seeds(32), translate_performance_Target100);
+
+    println!(
+        "variance between designs: {}, within designs: {}",
+        components.between_design,
+        components.within_design
+    );
+}
+
+#[cfg(test)]
+mod variance_decomposition_works {
+    use super::*;
+
+    #[test]
+    fn components_sum_to_the_total_variance() {
+        let design_variants = [0, 5, 9, 20];
+        let seeds = ensemble_seeds(8);
+
+        let components = variance_decomposition(&design_variants, &seeds, translate_performance_Target100);
+
+        let mut all = Vec::new();
+        for &m in &design_variants {
+            for &seed in &seeds {
+                all.push(performance_of_design_in_environment(
+                    translate_design_FortnightlyDeposit,
+                    p!(m),
+                    Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() }),
+                ));
+            }
+        }
+
+        let total = stats::variance(&all);
+
+        assert!((components.between_design + components.within_design - total).abs() < 0.01);
+        assert!(components.between_design > components.within_design);
+    }
+}
+
+#[cfg(test)]
+mod robust_optimal_deposit_uniform_works {
+    use super::*;
+
+    fn worst_case_final_deviation(deposit: Money, high: u32, periods: usize, target: Money) -> Money {
+        [0, high as Money - 1]
+            .into_iter()
+            .map(|withdrawal| {
+                let balances = simulate_balance(&interleave(
+                    vec![t!(d, deposit); periods],
+                    vec![t!(w, withdrawal); periods],
+                ));
+
+                (balances[balances.len() - 1] - target).abs()
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn matches_the_numerical_minimax() {
+        let (high, periods, target) = (5, ANNUAL_FORTNIGHTS as usize, 260);
+
+        let expected = robust_optimal_deposit_uniform(high, periods, target);
+        assert_eq!(expected, 12.);
+
+        let found = minimize(
+            |[x]| worst_case_final_deviation(x.round() as Money, high, periods, target) as f32,
+            regular_simplex_centered_at(8.0, [4.0]),
+            100
+        );
+
+        assert_eq!(found.xs[0].round(), expected);
+    }
+}
+
+#[cfg(test)]
+mod update_balances_works {
+    use super::*;
+
+    #[test]
+    fn matches_simulating_from_scratch() {
+        let mut transactions = translate_FortnightlyDepositAndRandomWithdrawal(p!(9));
+        let balances = simulate_balance(&transactions);
+
+        for (changed_index, delta) in [(0, 3), (7, -2), (transactions.len() - 1, 10)] {
+            let updated = update_balances(balances.clone(), changed_index, delta);
+
+            let original = transactions[changed_index];
+            let changed = original.signed_amount() + delta;
+            transactions[changed_index] = if changed >= 0 { t!(d, changed) } else { t!(w, -changed) };
+
+            assert_eq!(updated, simulate_balance(&transactions));
+
+            transactions[changed_index] = original;
+        }
+    }
+}
+
+#[cfg(test)]
+mod grade_performance_works {
+    use super::*;
+
+    #[test]
+    fn on_the_default_grades() {
+        assert_eq!(grade_performance(0., &GRADES), "excellent");
+        assert_eq!(grade_performance(5., &GRADES), "excellent");
+        assert_eq!(grade_performance(5.01, &GRADES), "good");
+        assert_eq!(grade_performance(61.89, &GRADES), "poor");
+        assert_eq!(grade_performance(Performance::NAN, &GRADES), "poor");
+    }
+
+    #[test]
+    fn on_no_grades() {
+        assert_eq!(grade_performance(1., &[]), "");
+    }
+}
+
+#[cfg(test)]
+mod simulate_years_works {
+    use super::*;
+
+    #[test]
+    fn without_carrying_repeats_each_year_from_zero() {
+        let balances = simulate_years(
+            translate_design_FortnightlyDeposit,
+            p!(10),
+            Environment::None,
+            3,
+            <_>::default(),
+            Rollover { carry_balance: false, annual_interest: 0.5 },
+        );
+
+        let year = simulate_balance(&translate_design_FortnightlyDeposit(p!(10)));
+
+        assert_eq!(balances, [year.clone(), year.clone(), year].concat());
+    }
+
+    #[test]
+    fn carrying_applies_interest_between_years() {
+        let balances = simulate_years(
+            translate_design_FortnightlyDeposit,
+            p!(10),
+            Environment::None,
+            2,
+            <_>::default(),
+            Rollover { carry_balance: true, annual_interest: 0.5 },
+        );
+
+        let year_length = ANNUAL_FORTNIGHTS as usize + 1;
+        assert_eq!(balances.len(), year_length * 2);
+        assert_eq!(balances[year_length - 1], 260);
+        assert_eq!(balances[year_length], 390);
+        assert_eq!(balances[balances.len() - 1], 650);
+    }
+
+    #[test]
+    fn saturates_instead_of_overflowing() {
+        let balances = simulate_years(
+            translate_design_FortnightlyDeposit,
+            p!(Money::MAX / 4),
+            Environment::None,
+            2,
+            <_>::default(),
+            Rollover { carry_balance: true, annual_interest: 1. },
+        );
+
+        assert_eq!(balances[balances.len() - 1], Money::MAX);
+    }
+}
+
+#[cfg(test)]
+mod sparkline_works {
+    use super::*;
+
+    #[test]
+    fn spans_the_min_to_the_max() {
+        assert_eq!(sparkline(&[0, 1, 2, 3, 4, 5, 6, 7]), "▁▂▃▄▅▆▇█");
+        assert_eq!(sparkline(&[-100, 100, 0]), "▁█▄");
+    }
+
+    #[test]
+    fn on_flat_and_empty_series() {
+        assert_eq!(sparkline(&[42, 42, 42]), "▁▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+}
+
+#[cfg(test)]
+mod expected_final_balance_works {
+    use super::*;
+
+    #[test]
+    fn matches_the_ensemble_mean() {
+        let deposit = 9;
+        let periods = ANNUAL_FORTNIGHTS as usize;
+
+        let final_balances = ensemble_seeds(1000)
+            .into_iter()
+            .map(|seed| {
+                let balances = simulate_balance(&interleave(
+                    translate_design_FortnightlyDeposit(p!(deposit)),
+                    translate_environment(Environment::FortnightlyRandomWithdrawal(
+                        FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() }
+                    )),
+                ));
+
+                balances[balances.len() - 1] as f32
+            })
+            .collect::<Vec<_>>();
+
+        let expected = expected_final_balance(
+            deposit,
+            Environment::FortnightlyRandomWithdrawal(<_>::default()),
+            periods
+        );
+
+        assert_eq!(expected, 182.);
+        // The standard error of the mean here is about a quarter.
+        assert!((stats::mean(&final_balances) - expected).abs() < 1.);
+    }
+
+    #[test]
+    fn without_an_environment_is_just_the_deposits() {
+        assert_eq!(expected_final_balance(9, Environment::None, 26), 234.);
+    }
+}
+
+#[cfg(test)]
+mod simulate_balance_streaming_works {
+    use super::*;
+
+    #[test]
+    fn matches_simulate_balance() {
+        let transactions = translate_FortnightlyDepositAndRandomWithdrawal(p!(9));
+
+        let mut balances = Vec::new();
+        let final_balance = simulate_balance_streaming(transactions.iter().copied(), transactions.len(), |b| balances.push(b));
+
+        assert_eq!(balances, simulate_balance(&transactions));
+        assert_eq!(final_balance, Ok(balances[balances.len() - 1]));
+    }
+
+    #[test]
+    fn stops_past_the_cap() {
+        let mut count = 0;
+        let result = simulate_balance_streaming(std::iter::repeat(t!(d, 1)), 10, |_| count += 1);
+
+        assert_eq!(result, Err(TransactionError::CapExceeded { cap: 10 }));
+        // The initial balance, then one per transaction.
+        assert_eq!(count, 11);
+    }
+}
+
+#[cfg(test)]
+mod fold_balance_works {
+    use super::*;
+
+    #[test]
+    fn matches_simulate_balance() {
+        let transactions = translate_FortnightlyDepositAndRandomWithdrawal(p!(9));
+        let balances = simulate_balance(&transactions);
+
+        let collected = fold_balance(&transactions, Vec::new(), |mut v, b| { v.push(b); v });
+        assert_eq!(collected, balances);
+
+        assert_eq!(fold_balance(&transactions, Money::MAX, Money::min), *balances.iter().min().unwrap());
+        assert_eq!(fold_balance(&transactions, 0, |count, _| count + 1), balances.len());
+    }
+}
+
+/// Pinned outputs of a canonical run, so that any change to the RNG, the simulation, or the simplex
+/// maths shows up as a failure here, and can then be judged deliberately.
+///
+/// The integer paths are compared exactly. The `f32` paths are compared to within `F32_TOLERANCE`.
+/// The basic IEEE 754 operations and `sqrt` are correctly rounded, and Rust doesn't fuse
+/// multiply-adds unless asked to, so on mainstream platforms these should match bit for bit. The
+/// tolerance is there for platforms that don't promise that, (e.g. x87 style extended precision).
+/// It is kept tight on purpose: `minimize` makes discrete choices based on comparisons, so a
+/// larger difference usually means the simplex took a different path, which is worth knowing about.
+#[cfg(test)]
+mod golden_values {
+    use super::*;
+
+    const F32_TOLERANCE: f32 = 1e-4;
+
+    macro_rules! approx_eq {
+        ($a: expr, $b: expr) => {
+            assert!(($a - $b).abs() < F32_TOLERANCE, "{} != {}", $a, $b);
+        };
+    }
+
+    #[test]
+    fn xs_stream() {
+        let mut rng = xs::from_seed(<_>::default());
+
+        assert_eq!(
+            (0..4).map(|_| xs::range(&mut rng, 0..1000)).collect::<Vec<_>>(),
+            [576, 405, 576, 150]
+        );
+
+        assert_eq!(
+            ensemble_seeds(2),
+            [
+                [152, 95, 173, 11, 237, 94, 173, 11, 152, 95, 173, 11, 54, 50, 150, 106],
+                [99, 241, 166, 11, 204, 156, 157, 106, 152, 95, 173, 11, 89, 219, 113, 208],
+            ]
+        );
+    }
+
+    #[test]
+    fn random_withdrawal_balances() {
+        assert_eq!(
+            simulate_balance(&translate_environment_FortnightlyRandomWithdrawal(<_>::default())),
+            [0, -1, -1, -2, -2, -4, -7, -8, -12, -15, -19, -23, -25, -26, -28, -30, -34, -34, -36, -40, -44, -46, -50, -51, -54, -54, -56]
+        );
+
+        assert_eq!(
+            simulate_balance(&translate_FortnightlyDepositAndRandomWithdrawal(p!(9))),
+            [
+                0, 9, 8, 17, 17, 26, 25, 34, 34, 43, 41, 50, 47, 56, 55, 64, 60, 69, 66, 75, 71, 80, 76, 85, 83, 92, 91,
+                100, 98, 107, 105, 114, 110, 119, 119, 128, 126, 135, 131, 140, 136, 145, 143, 152, 148, 157, 156, 165,
+                162, 171, 171, 180, 178
+            ]
+        );
+    }
+
+    #[test]
+    fn regular_simplex_vertices() {
+        let simplex = minimize::regular_simplex::<2>();
+
+        approx_eq!(simplex[0][0], 0.5576775);
+        approx_eq!(simplex[0][1], -0.14942925);
+        approx_eq!(simplex[1][0], -0.14942925);
+        approx_eq!(simplex[1][1], 0.5576775);
+        approx_eq!(simplex[2][0], -0.40824828);
+        approx_eq!(simplex[2][1], -0.40824828);
+    }
+
+    #[test]
+    fn design_1_minimum() {
+        let minimum = minimize(
+            |[x]| performance_of_design(translate_design_FortnightlyDeposit, p!(x.round() as i32)),
+            regular_simplex_centered_at(100.0, [50.0]),
+            100
+        );
+
+        approx_eq!(minimum.xs[0], 4.6875);
+        approx_eq!(minimum.y, 42.77778);
+    }
+
+    #[test]
+    fn alternative_design_minimum() {
+        let minimum = minimize(
+            |[a, b]| performance_of_design(
+                translate_InitialAndFortnightlyDepositAndRandomWithdrawal,
+                p!(a.round() as i32, b.round() as i32)
+            ),
+            regular_simplex_centered_at(10.0, [100.0, 3.0]),
+            100,
+        );
+
+        approx_eq!(minimum.xs[0], 101.27954);
+        approx_eq!(minimum.xs[1], 1.5450332);
+        approx_eq!(minimum.y, 4.9444447);
+    }
+}
+
+#[cfg(test)]
+mod no_op_works {
+    use super::*;
+
+    #[test]
+    fn leaves_the_balance_alone_and_is_not_counted() {
+        let transactions = [t!(d, 10), t!(n), t!(w, 5), t!(n)];
+
+        assert_eq!(simulate_balance(&transactions), [0, 10, 10, 5, 5]);
+        assert_eq!(count_transactions(&transactions), 2);
+    }
+}
+
+#[cfg(test)]
+mod scenario_weighted_performance_works {
+    use super::*;
+
+    #[test]
+    fn weights_each_scenario_by_its_probability() {
+        let normal_year = Environment::FortnightlyRandomWithdrawal(<_>::default());
+        let recession = Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { high: 15, ..<_>::default() });
+
+        let normal_performance = performance_of_design_in_environment(translate_design_FortnightlyDeposit, p!(9), normal_year);
+        let recession_performance = performance_of_design_in_environment(translate_design_FortnightlyDeposit, p!(9), recession);
+
+        let weighted = scenario_weighted_performance(translate_design_FortnightlyDeposit, p!(9), &[(normal_year, 0.8), (recession, 0.2)]);
+        assert!((weighted - (0.8 * normal_performance + 0.2 * recession_performance)).abs() < 0.001);
+
+        let relative = scenario_weighted_performance(translate_design_FortnightlyDeposit, p!(9), &[(normal_year, 4.), (recession, 1.)]);
+        assert!((weighted - relative).abs() < 0.001);
+
+        let certain = scenario_weighted_performance(translate_design_FortnightlyDeposit, p!(9), &[(normal_year, 1.)]);
+        assert_eq!(certain, normal_performance);
+    }
+}
+
+#[cfg(test)]
+mod periods_to_break_even_works {
+    use super::*;
+
+    #[test]
+    fn against_expected_withdrawals() {
+        let environment = Environment::FortnightlyRandomWithdrawal(<_>::default());
+
+        // 0 > 2, 3 > 4 and 6 > 6 are all false, but 9 > 8.
+        assert_eq!(periods_to_break_even(translate_design_InitialAndFortnightlyDeposit, p!(0, 3), environment), Some(4));
+        assert_eq!(periods_to_break_even(translate_design_FortnightlyDeposit, p!(9), environment), Some(1));
+        assert_eq!(periods_to_break_even(translate_design_FortnightlyDeposit, p!(2), environment), None);
+        assert_eq!(periods_to_break_even(translate_design_FortnightlyDeposit, p!(0), Environment::None), None);
+    }
+}
+
+#[cfg(test)]
+mod downsample_works {
+    use super::*;
+
+    #[test]
+    fn keeps_the_extremes_in_order() {
+        let balances = [0, 5, -3, 2, 9, 1, 4, 4];
+
+        assert_eq!(downsample(&balances, 4), [5, -3, 9, 1]);
+        assert_eq!(downsample(&balances, 2), [-3, 9]);
+        assert_eq!(downsample(&balances, 1), [-3]);
+        assert_eq!(downsample(&balances, 0), []);
+    }
+
+    #[test]
+    fn leaves_short_series_alone() {
+        assert_eq!(downsample(&[1, 2, 3], 3), [1, 2, 3]);
+        assert_eq!(downsample(&[1, 2, 3], 100), [1, 2, 3]);
+    }
+
+    #[test]
+    fn never_exceeds_max_points() {
+        let balances = (0..1000).collect::<Vec<_>>();
+
+        for max_points in 0..50 {
+            assert!(downsample(&balances, max_points).len() <= max_points);
+        }
+    }
+}
+
+#[cfg(test)]
+mod recommend_seed_count_works {
+    use super::*;
+
+    #[test]
+    fn hits_the_target_stderr() {
+        let target_stderr = 0.2;
+        let count = recommend_seed_count(translate_design_FortnightlyDeposit, p!(9), target_stderr);
+
+        let performances = ensemble_seeds(count)
+            .into_iter()
+            .map(|seed| performance_of_design_in_environment(
+                translate_design_FortnightlyDeposit,
+                p!(9),
+                Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() }),
+            ))
+            .collect::<Vec<_>>();
+
+        // The recommendation is based on an estimate of the spread, so allow for some error in it.
+        assert!(stats::standard_error(&performances) < target_stderr * 1.2);
+    }
+
+    #[test]
+    fn ensemble_seeds_are_a_prefix_of_the_stream() {
+        let seeds = ensemble_seeds(10);
+
+        assert_eq!(ensemble_seeds(4), seeds[..4]);
+        assert_eq!(ensemble_seed_stream().take(10).collect::<Vec<_>>(), seeds);
+    }
+
+    #[test]
+    fn needs_more_for_a_smaller_target() {
+        let loose = recommend_seed_count(translate_design_FortnightlyDeposit, p!(9), 0.4);
+        let tight = recommend_seed_count(translate_design_FortnightlyDeposit, p!(9), 0.1);
+
+        assert!(tight > loose * 8, "{tight} vs {loose}");
+        assert_eq!(recommend_seed_count(translate_design_FortnightlyDeposit, p!(9), 1000.), 2);
+    }
+}
+
+#[cfg(test)]
+mod performance_of_design_smooth_works {
+    use super::*;
+
+    #[test]
+    fn matches_at_whole_amounts() {
+        for design in [p!(0, 0), p!(5, 0), p!(90, 1), p!(100, 3)] {
+            assert_eq!(
+                performance_of_design_smooth(translate_design_InitialAndFortnightlyDeposit, (design.0 as f32, design.1 as f32)),
+                performance_of_design(translate_design_InitialAndFortnightlyDeposit, design),
+            );
+        }
+    }
+
+    #[test]
+    fn interpolates_between_whole_amounts() {
+        let at_5 = performance_of_design(translate_design_FortnightlyDeposit, p!(5));
+        let at_6 = performance_of_design(translate_design_FortnightlyDeposit, p!(6));
+
+        let quarter = performance_of_design_smooth(translate_design_FortnightlyDeposit, (5.25, 0.));
+        assert!((quarter - (0.75 * at_5 + 0.25 * at_6)).abs() < 0.001);
+    }
+
+    #[test]
+    fn snaps_to_the_best_corner() {
+        // From the sweep, 5 is the best whole fortnightly deposit.
+        assert_eq!(best_whole_design_near(translate_design_FortnightlyDeposit, (5.9, 0.)), p!(5, 0));
+        assert_eq!(best_whole_design_near(translate_design_FortnightlyDeposit, (4.1, 0.)), p!(5, 0));
+    }
+}
+
+#[cfg(test)]
+mod autocorrelation_works {
+    use super::*;
+
+    macro_rules! approx_eq {
+        ($a: expr, $b: expr) => {
+            assert!(($a - $b).abs() < 0.0001, "{} != {}", $a, $b);
+        };
+    }
+
+    #[test]
+    fn on_alternating_series() {
+        let output = autocorrelation(&[1, -1, 1, -1, 1, -1], 2);
+
+        assert_eq!(output.len(), 3);
+        approx_eq!(output[0], 1.);
+        // 5 of the 6 products at lag 1 are -1, and 4 at lag 2 are 1, each over a sum of squares of 6.
+        approx_eq!(output[1], -5. / 6.);
+        approx_eq!(output[2], 4. / 6.);
+    }
+
+    #[test]
+    fn on_constant_and_short_series() {
+        assert_eq!(autocorrelation(&[7, 7, 7], 5), [1., 1., 1.]);
+        assert_eq!(autocorrelation(&[7], 5), [1.]);
+        assert_eq!(autocorrelation(&[], 5), []);
+    }
+}
+
+#[cfg(test)]
+mod halton_starts_works {
+    use super::*;
+
+    #[test]
+    fn follows_the_sequence() {
+        let unit = DesignSpace { x1: (0., 1.), x2: (0., 1.) };
+
+        assert_eq!(
+            halton_starts(unit, 4),
+            [[0.5, 1. / 3.], [0.25, 2. / 3.], [0.75, 1. / 9.], [0.125, 4. / 9.]]
+        );
+    }
+
+    #[test]
+    fn stays_in_the_space() {
+        for [x1, x2] in halton_starts(ALTERNATIVE_DESIGN_SPACE, 100) {
+            assert!((90. ..115.).contains(&x1));
+            assert!((0. ..6.).contains(&x2));
+        }
+    }
+}
+
+#[cfg(test)]
+mod random_starts_works {
+    use super::*;
+
+    #[test]
+    fn stays_in_the_space() {
+        for seed in [<_>::default(), [3; 16], [200; 16]] {
+            for [x1, x2] in random_starts(ALTERNATIVE_DESIGN_SPACE, 1000, seed) {
+                assert!((90. ..115.).contains(&x1));
+                assert!((0. ..6.).contains(&x2));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod resample_transactions_works {
+    use super::*;
+
+    #[test]
+    fn nets_out_each_bucket() {
+        let transactions = [t!(d, 10), t!(w, 3), t!(n), t!(w, 4), t!(w, 6), t!(d, 10), t!(d, 1)];
+
+        let resampled = resample_transactions(&transactions, 2);
+        assert_eq!(simulate_balance(&resampled), [0, 7, 3, 7, 8]);
+        assert_eq!(count_transactions(&resampled), 4);
+
+        let resampled = resample_transactions(&transactions, 3);
+        assert_eq!(simulate_balance(&resampled), [0, 7, 7, 8]);
+        // The middle bucket nets out to nothing.
+        assert_eq!(count_transactions(&resampled), 2);
+    }
+
+    #[test]
+    fn keeps_the_balances_at_the_bucket_boundaries() {
+        let transactions = translate_FortnightlyDepositAndRandomWithdrawal(p!(9));
+        let balances = simulate_balance(&transactions);
+
+        let resampled_balances = simulate_balance(&resample_transactions(&transactions, 2));
+
+        for (i, &b) in resampled_balances.iter().enumerate() {
+            assert_eq!(b, balances[i * 2]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod min_safe_deposit_works {
+    use super::*;
+
+    #[test]
+    fn is_the_smallest_safe_deposit() {
+        let seeds = ensemble_seeds(32);
+        let periods = ANNUAL_FORTNIGHTS as usize;
+
+        let ruins = |deposit| ruined_seed_count(deposit, &seeds, periods) > 0;
+
+        let deposit = min_safe_deposit(&seeds, periods);
+
+        assert!(!ruins(deposit));
+        assert!(deposit == 0 || ruins(deposit - 1));
+    }
+
+    #[test]
+    fn ruined_seed_count_counts_each_seed_once() {
+        let seeds = ensemble_seeds(8);
+        let periods = ANNUAL_FORTNIGHTS as usize;
+        let high = FortnightlyRandomWithdrawalArgs::default().high as Money;
+
+        // Some withdrawal in a year will not be 0, so with nothing deposited every seed is ruined.
+        assert_eq!(ruined_seed_count(0, &seeds, periods), 8);
+        // While a deposit as big as any withdrawal can't be.
+        assert_eq!(ruined_seed_count(high - 1, &seeds, periods), 0);
+        assert_eq!(ruined_seed_count(0, &seeds, 0), 0);
+    }
+
+    #[test]
+    fn with_no_periods_nothing_is_needed() {
+        assert_eq!(min_safe_deposit(&ensemble_seeds(4), 0), 0);
+    }
+
+    #[test]
+    fn simulate_until_ruin_finds_the_first_negative_balance() {
+        assert_eq!(simulate_until_ruin(&[t!(d, 2), t!(w, 2), t!(w, 1), t!(d, 5)]), Some(2));
+        assert_eq!(simulate_until_ruin(&[t!(d, 2), t!(w, 2)]), None);
+    }
+}
+
+#[cfg(test)]
+mod format_performance_works {
+    use super::*;
+
+    #[test]
+    fn at_each_precision() {
+        let value = 43.886_79;
+
+        assert_eq!(format_performance(value, Precision::Decimals(2)), "43.89");
+        assert_eq!(format_performance(value, Precision::Decimals(4)), "43.8868");
+        assert_eq!(format_performance(value, Precision::Decimals(0)), "44");
+        assert_eq!(format_performance(value, Precision::Full), "43.88679");
+        assert_eq!(format_performance(value, Precision::Full).parse::<Performance>(), Ok(value));
+    }
+}
+
+#[cfg(test)]
+mod performance_gradient_field_works {
+    use super::*;
+
+    #[test]
+    fn points_uphill_from_the_minimum() {
+        // From the sweep, the best whole fortnightly deposit is 5. That design ignores the second
+        // parameter, which should show up as no gradient in that direction.
+        let space = DesignSpace { x1: (1., 11.), x2: (1., 3.) };
+
+        let field = performance_gradient_field(translate_design_FortnightlyDeposit, space, 5);
+        assert_eq!(field.len(), 25);
+
+        for ((x1, _), (dx1, dx2)) in field {
+            if x1 < 5. {
+                assert!(dx1 < 0., "{x1}: {dx1}");
+            } else if x1 > 6. {
+                assert!(dx1 > 0., "{x1}: {dx1}");
+            }
+            assert_eq!(dx2, 0.);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tagged_transaction_works {
+    use super::*;
+
+    #[test]
+    fn tags_do_not_change_the_balances() {
+        let design = translate_design_InitialAndFortnightlyDeposit(p!(90, 1));
+        let environment = translate_environment_FortnightlyRandomWithdrawal(<_>::default());
+
+        let tagged = interleave_tagged(design.clone(), environment.clone());
+
+        assert_eq!(simulate_balance(&tagged), simulate_balance(&interleave(design, environment)));
+    }
+
+    #[test]
+    fn tags_each_transaction_with_its_source() {
+        let tagged = interleave_tagged(vec![t!(d, 5), t!(d, 6), t!(d, 7)], vec![t!(w, 1)]);
+
+        assert_eq!(
+            tagged.iter().map(|t| t.source).collect::<Vec<_>>(),
+            [Source::Design, Source::Environment, Source::Design, Source::Design]
+        );
+        assert_eq!(net_flow_from(&tagged, Source::Design), 18);
+        assert_eq!(net_flow_from(&tagged, Source::Environment), -1);
+    }
+}
+
+#[cfg(test)]
+mod baseline_performance_works {
+    use super::*;
+
+    #[test]
+    fn without_an_environment_stays_at_zero() {
+        for periods in [0, 1, 26, 100] {
+            assert_eq!(baseline_performance(Environment::None, translate_performance_Target100, periods), 100.);
+        }
+    }
+
+    #[test]
+    fn is_just_the_withdrawals() {
+        let args = FortnightlyRandomWithdrawalArgs { seed: ensemble_seeds(1)[0], periods: 40, ..<_>::default() };
+
+        let mut balance = 0;
+        let mut balances = vec![balance];
+        for t in translate_environment_FortnightlyRandomWithdrawal(args) {
+            balance -= t.amount;
+            balances.push(balance);
+        }
+
+        assert_eq!(balances.len(), 41);
+        assert_eq!(
+            baseline_performance(
+                Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { periods: 1, ..args }),
+                translate_performance_Target100,
+                40
+            ),
+            translate_performance_Target100(&balances)
+        );
+    }
+}
+
+#[cfg(test)]
+mod adaptive_sweep_works {
+    use super::*;
+
+    #[test]
+    fn without_a_budget_is_the_corner_grid() {
+        let calls = adaptive_sweep(translate_design_InitialAndFortnightlyDeposit, ALTERNATIVE_DESIGN_SPACE, 4, 0);
+
+        assert_eq!(calls.len(), 25);
+        for ((x1, x2), performance) in calls {
+            assert_eq!(performance, performance_of_design(translate_design_InitialAndFortnightlyDeposit, p!(x1 as i32, x2 as i32)));
+        }
+    }
+
+    #[test]
+    fn stays_within_the_budget_and_the_space() {
+        let budget = 103;
+        let calls = adaptive_sweep(translate_InitialAndFortnightlyDepositAndRandomWithdrawal, ALTERNATIVE_DESIGN_SPACE, 4, budget);
+
+        assert!(calls.len() > 25);
+        assert!(calls.len() <= 25 + budget);
+
+        let mut points = calls.iter().map(|&((x1, x2), _)| (x1.to_bits(), x2.to_bits())).collect::<Vec<_>>();
+        points.sort();
+        points.dedup();
+        assert_eq!(points.len(), calls.len());
+
+        for ((x1, x2), _) in calls {
+            assert!((90. ..=115.).contains(&x1));
+            assert!((0. ..=6.).contains(&x2));
+        }
+    }
+
+    #[test]
+    fn stops_early_when_everything_is_flat() {
+        // This design ignores the second parameter, and the space keeps the first one fixed.
+        let space = DesignSpace { x1: (5., 5.), x2: (0., 6.) };
+        let calls = adaptive_sweep(translate_design_FortnightlyDeposit, space, 2, 1000);
+
+        // The corners from the two columns coincide, leaving 3 distinct rows.
+        assert_eq!(calls.len(), 3);
+    }
+}
+
+#[cfg(test)]
+mod sweep_into_works {
+    use super::*;
+
+    #[test]
+    fn reuses_the_buffer() {
+        let mut buf = Vec::new();
+
+        sweep_into(&mut buf, translate_design_InitialAndFortnightlyDeposit, ALTERNATIVE_DESIGN_SPACE, 10);
+        assert_eq!(buf.len(), 100);
+        let capacity = buf.capacity();
+        let pointer = buf.as_ptr();
+
+        sweep_into(&mut buf, translate_design_FortnightlyDeposit, ALTERNATIVE_DESIGN_SPACE, 8);
+        assert_eq!(buf.len(), 64);
+        assert_eq!(buf.capacity(), capacity);
+        assert_eq!(buf.as_ptr(), pointer);
+
+        assert_eq!(buf[0], ((90., 0.), performance_of_design(translate_design_FortnightlyDeposit, p!(90, 0))));
+        assert_eq!(buf[63], ((111.875, 5.25), performance_of_design(translate_design_FortnightlyDeposit, p!(111, 5))));
+    }
+
+    #[test]
+    fn matches_the_alternative_design_sweep() {
+        let mut buf = vec![((0., 0.), 0.); 3];
+
+        sweep_into(&mut buf, translate_InitialAndFortnightlyDepositAndRandomWithdrawal, ALTERNATIVE_DESIGN_SPACE, 50);
+
+        assert_eq!(buf, sample_performance_of_alternative_design());
+    }
+}
+
+#[cfg(test)]
+mod count_local_minima_works {
+    use super::*;
+
+    #[test]
+    fn on_simple_shapes() {
+        assert_eq!(count_local_minima(&[]), 0);
+        assert_eq!(count_local_minima(&[1.]), 1);
+        assert_eq!(count_local_minima(&[3., 2., 1., 2., 3.]), 1);
+        assert_eq!(count_local_minima(&[1., 2., 3.]), 1);
+        assert_eq!(count_local_minima(&[3., 1., 2., 0., 4.]), 2);
+        assert_eq!(count_local_minima(&[1., 2., 1.]), 2);
+    }
+
+    #[test]
+    fn on_plateaus() {
+        assert_eq!(count_local_minima(&[2., 2., 2.]), 1);
+        assert_eq!(count_local_minima(&[3., 1., 1., 1., 3.]), 1);
+        assert_eq!(count_local_minima(&[3., 2., 2., 1., 3.]), 1);
+        assert_eq!(count_local_minima(&[1., 1., 3., 2., 2.]), 2);
+    }
+}
+
+#[cfg(test)]
+mod signed_amount_works {
+    use super::*;
+
+    #[test]
+    fn on_each_kind() {
+        assert_eq!(t!(d, 5).signed_amount(), 5);
+        assert_eq!(t!(w, 5).signed_amount(), -5);
+        assert_eq!(t!(n).signed_amount(), 0);
+    }
+}
+
+#[cfg(test)]
+mod sample_performance_of_design_split_works {
+    use super::*;
+
+    #[test]
+    fn parallel_matches_serial() {
+        let environment = Environment::FortnightlyRandomWithdrawal(<_>::default());
+        let design_variants = (0..23).collect::<Vec<_>>();
+        let base_seed = [7; 16];
+
+        let serial = sample_performance_of_design_split(translate_design_FortnightlyDeposit, environment, &design_variants, base_seed);
+
+        for threads in [1, 2, 3, 8, 64] {
+            assert_eq!(
+                sample_performance_of_design_split_parallel(translate_design_FortnightlyDeposit, environment, &design_variants, base_seed, threads),
+                serial,
+                "{threads} threads"
+            );
+        }
+    }
+
+    #[test]
+    fn gives_each_design_its_own_environment() {
+        let environment = Environment::FortnightlyRandomWithdrawal(<_>::default());
+
+        let performances = sample_performance_of_design_split(translate_design_FortnightlyDeposit, environment, &[9; 8], <_>::default());
+
+        assert!(performances.iter().any(|&p| p != performances[0]), "{performances:?}");
+    }
+
+    #[test]
+    fn split_streams_are_deterministic_and_distinct() {
+        let seed = <_>::default();
+
+        assert_eq!(xs::split(seed, 3), xs::split(seed, 3));
+
+        let seeds = (0..100).map(|i| xs::split(seed, i)).collect::<Vec<_>>();
+        for i in 0..seeds.len() {
+            for j in i + 1..seeds.len() {
+                assert_ne!(seeds[i], seeds[j]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod required_deposit_for_goal_works {
+    use super::*;
+
+    #[test]
+    fn without_withdrawals() {
+        assert_eq!(required_deposit_for_goal(2600, 26, 0.), 100.);
+    }
+
+    #[test]
+    fn inverts_expected_final_balance() {
+        let environment = Environment::FortnightlyRandomWithdrawal(<_>::default());
+
+        let deposit = required_deposit_for_goal(260, 26, environment.mean_withdrawal());
+
+        assert_eq!(deposit, 12.);
+        assert_eq!(expected_final_balance(deposit as Money, environment, 26), 260.);
+    }
+}
+
+#[cfg(test)]
+mod validate_transactions_works {
+    use super::*;
+
+    #[test]
+    fn accepts_designs_and_environments() {
+        assert_eq!(validate_transactions(&translate_FortnightlyDepositAndRandomWithdrawal(p!(9))), Ok(()));
+        assert_eq!(validate_transactions(&[t!(d, 0), t!(n), t!(w, 0)]), Ok(()));
+        assert_eq!(validate_transactions(&[]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_negative_amounts_with_the_first_index() {
+        assert_eq!(
+            validate_transactions(&[t!(d, 3), t!(w, -2), t!(d, -1)]),
+            Err(ValidationError::SignContradictsKind { index: 1 })
+        );
+        assert_eq!(
+            validate_transactions(&[t!(n), t!(d, -1)]),
+            Err(ValidationError::SignContradictsKind { index: 1 })
+        );
+    }
+}
+
+#[cfg(test)]
+mod expected_shortfall_works {
+    use super::*;
+
+    #[test]
+    fn averages_the_highest() {
+        let performances = [5., 1., 9., 3., 7., 2., 8., 4., 6., 10.];
+
+        assert_eq!(expected_shortfall(&performances, 0.1), 10.);
+        assert_eq!(expected_shortfall(&performances, 0.3), 9.);
+        assert_eq!(expected_shortfall(&performances, 1.), 5.5);
+    }
+
+    #[test]
+    fn rounds_the_tail_up() {
+        assert_eq!(expected_shortfall(&[1., 2., 3.], 0.01), 3.);
+        assert_eq!(expected_shortfall(&[1., 2., 3.], 0.5), 2.5);
+    }
+
+    #[test]
+    fn takes_everything_at_an_alpha_of_1() {
+        assert_eq!(expected_shortfall(&[1., 2., 3.], 1.), stats::mean(&[1., 2., 3.]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_an_alpha_of_0() {
+        expected_shortfall(&[1., 2., 3.], 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_an_alpha_above_1() {
+        expected_shortfall(&[1., 2., 3.], 1.5);
+    }
+}
+
+#[cfg(test)]
+mod simulate_balance_attributed_works {
+    use super::*;
+
+    #[test]
+    fn pairs_each_balance_with_its_source() {
+        let tagged = interleave_tagged(vec![t!(d, 5), t!(d, 6)], vec![t!(w, 1), t!(w, 2), t!(w, 3)]);
+
+        assert_eq!(
+            simulate_balance_attributed(&tagged),
+            vec![
+                (5, Source::Design),
+                (4, Source::Environment),
+                (10, Source::Design),
+                (8, Source::Environment),
+                (5, Source::Environment),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_simulate_balance() {
+        let tagged = interleave_tagged(
+            translate_design_FortnightlyDeposit(p!(9)),
+            translate_environment(Environment::FortnightlyRandomWithdrawal(<_>::default())),
+        );
+
+        let balances = simulate_balance_attributed(&tagged).into_iter().map(|(balance, _)| balance).collect::<Vec<_>>();
+
+        assert_eq!(balances, simulate_balance(&tagged)[1..]);
+    }
+}
+
+#[cfg(test)]
+mod robustness_score_works {
+    use super::*;
+
+    #[test]
+    fn is_zero_without_variance() {
+        assert_eq!(robustness_score(&[3., 3., 3.]), 0.);
+        assert_eq!(robustness_score(&[0., 0.]), 0.);
+    }
+
+    #[test]
+    fn is_the_coefficient_of_variation() {
+        // A sample standard deviation of 2, and a mean of 4.
+        assert_eq!(robustness_score(&[2., 4., 6.]), 0.5);
+        // So doubling every performance leaves it the same.
+        assert_eq!(robustness_score(&[4., 8., 12.]), 0.5);
+        assert_eq!(robustness_score(&[-1., 1.]), f32::INFINITY);
+    }
+}
+
+#[cfg(test)]
+mod marginal_performance_per_dollar_works {
+    use super::*;
+
+    #[test]
+    fn matches_the_one_dollar_difference() {
+        assert_eq!(
+            marginal_performance_per_dollar(translate_design_FortnightlyDeposit, p!(2), 0, translate_performance_Target100),
+            performance_of_design(translate_design_FortnightlyDeposit, p!(2)) - performance_of_design(translate_design_FortnightlyDeposit, p!(3))
+        );
+    }
+
+    #[test]
+    fn changes_sign_past_the_target() {
+        assert!(marginal_performance_per_dollar(translate_design_FortnightlyDeposit, p!(1), 0, translate_performance_Target100) > 0.);
+        assert!(marginal_performance_per_dollar(translate_design_FortnightlyDeposit, p!(20), 0, translate_performance_Target100) < 0.);
+    }
+
+    #[test]
+    fn bumps_only_the_chosen_parameter() {
+        let design = translate_design_InitialAndFortnightlyDeposit;
+
+        assert_eq!(
+            marginal_performance_per_dollar(design, p!(90, 1), 1, translate_performance_Target100),
+            performance_of_design(design, p!(90, 1)) - performance_of_design(design, p!(90, 2))
+        );
+        assert_eq!(
+            marginal_performance_per_dollar(design, p!(90, 1), 0, translate_performance_Target100),
+            performance_of_design(design, p!(90, 1)) - performance_of_design(design, p!(91, 1))
+        );
+    }
+}
+
+#[cfg(test)]
+mod sustainable_withdrawal_rate_works {
+    use super::*;
+
+    #[test]
+    fn inverts_required_deposit_for_goal() {
+        for (deposit, goal, periods) in [(9, 0, 26), (20, 260, 26), (0, 1, 1), (100, 30, 3), (7, 10, 4), (5, -40, 8)] {
+            let rate = sustainable_withdrawal_rate(deposit, goal, periods);
+
+            assert_eq!(required_deposit_for_goal(goal, periods, rate), deposit as f32, "{deposit} {goal} {periods}");
+        }
+    }
+
+    #[test]
+    fn leaves_the_goals_share_of_each_deposit() {
+        assert_eq!(sustainable_withdrawal_rate(20, 260, 26), 10.);
+        assert_eq!(sustainable_withdrawal_rate(20, 260, 13), 0.);
+        assert_eq!(sustainable_withdrawal_rate(20, 520, 13), -20.);
+    }
+
+    #[test]
+    fn breaks_even_in_expectation() {
+        let environment = Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { high: 19, ..<_>::default() });
+
+        assert_eq!(sustainable_withdrawal_rate(9, 0, 26), environment.mean_withdrawal());
+        assert_eq!(expected_final_balance(9, environment, 26), 0.);
+    }
+
+    #[test]
+    fn is_unlimited_over_no_periods() {
+        assert_eq!(sustainable_withdrawal_rate(9, 0, 0), f32::INFINITY);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_a_positive_goal_over_no_periods() {
+        sustainable_withdrawal_rate(9, 1, 0);
+    }
+}
+
+#[cfg(test)]
+mod transaction_count_penalty_works {
+    use super::*;
+
+    #[test]
+    fn skips_no_ops() {
+        assert_eq!(translate_performance_TransactionCountPenalty(&[t!(d, 1), t!(n), t!(w, 1)], 1.5), 3.);
+        assert_eq!(translate_performance_TransactionCountPenalty(&[], 1.5), 0.);
+    }
+
+    #[test]
+    fn penalizes_the_design() {
+        let base = performance_of_design(translate_design_FortnightlyDeposit, p!(9));
+
+        assert_eq!(performance_of_design_penalized(translate_design_FortnightlyDeposit, p!(9), translate_performance_Target100, 0.), base);
+        assert_eq!(performance_of_design_penalized(translate_design_FortnightlyDeposit, p!(9), translate_performance_Target100, 0.5), base + 13.);
+    }
+
+    #[test]
+    fn penalizes_the_given_metric() {
+        let final_balance: PerformanceTranslator = |balances| balances[balances.len() - 1] as Performance;
+
+        assert_eq!(
+            performance_of_design_penalized(translate_design_FortnightlyDeposit, p!(9), final_balance, 0.5),
+            9. * ANNUAL_FORTNIGHTS as Performance + 13.
+        );
+    }
+}
+
+#[cfg(test)]
+mod optimal_design_vs_environment_works {
+    use super::*;
+
+    #[test]
+    fn matches_an_exhaustive_search() {
+        let exhaustive = |objective: &dyn Fn(Money) -> Performance| {
+            (0..40).min_by(|&a, &b| objective(a).total_cmp(&objective(b))).unwrap()
+        };
+
+        let results = optimal_design_vs_environment(&[1, 5, 20], exhaustive, translate_performance_Target100);
+
+        assert_eq!(results.len(), 3);
+        for (high, deposit, performance) in results {
+            let environment = Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { high, ..<_>::default() });
+            assert_eq!(performance, performance_of_design_in_environment(translate_design_FortnightlyDeposit, p!(deposit), environment));
+            for other in 0..40 {
+                assert!(performance <= performance_of_design_in_environment(translate_design_FortnightlyDeposit, p!(other), environment));
+            }
+        }
+    }
+
+    #[test]
+    fn deposits_more_as_spending_rises() {
+        let exhaustive = |objective: &dyn Fn(Money) -> Performance| {
+            (0..40).min_by(|&a, &b| objective(a).total_cmp(&objective(b))).unwrap()
+        };
+
+        let results = optimal_design_vs_environment(&[1, 10, 30], exhaustive, translate_performance_Target100);
+
+        assert!(results[0].1 < results[1].1 && results[1].1 < results[2].1, "{results:?}");
+    }
+}
+
+#[cfg(test)]
+mod compare_designs_works {
+    use super::*;
+
+    #[test]
+    fn ranks_by_performance() {
+        let ranked = compare_designs(
+            &[
+                ("a", translate_design_FortnightlyDeposit, p!(1)),
+                ("b", translate_design_FortnightlyDeposit, p!(4)),
+                ("c", translate_design_InitialAndFortnightlyDeposit, p!(90, 1)),
+            ],
+            Environment::None,
+        );
+
+        assert_eq!(ranked.iter().map(|&(name, ..)| name).collect::<Vec<_>>(), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn breaks_ties_by_name_then_total_deposits() {
+        // Every balance of both is exactly 100 away from the target: 0, 0 for `under`, and 0, 200, 0
+        // for `over`, so they perform the same.
+        let under = |(deposit, _): DesignParameters| vec![t!(d, deposit)];
+        let over = |(deposit, _): DesignParameters| vec![t!(d, deposit), t!(w, 2 * deposit - 200)];
+        let tie = [
+            ("zed", under as DesignTranslator, p!(0)),
+            ("alpha", over as DesignTranslator, p!(200)),
+            ("alpha", under as DesignTranslator, p!(0)),
+        ];
+        assert_eq!(performance_of_design(tie[0].1, tie[0].2), performance_of_design(tie[1].1, tie[1].2));
+        assert_eq!(performance_of_design(tie[0].1, tie[0].2), performance_of_design(tie[2].1, tie[2].2));
+
+        for order in [[0, 1, 2], [2, 1, 0], [1, 0, 2]] {
+            let designs = order.map(|i| tie[i]);
+
+            let ranked = compare_designs(&designs, Environment::None);
+
+            assert_eq!(
+                ranked.iter().map(|&(name, design, _)| (name, design)).collect::<Vec<_>>(),
+                [("alpha", p!(0)), ("alpha", p!(200)), ("zed", p!(0))]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod export_run_as_code_works {
+    use super::*;
+
+    #[test]
+    fn writes_out_every_setting() {
+        let code = export_run_as_code(&ExperimentConfig {
+            design: "InitialAndFortnightlyDeposit",
+            design_parameters: p!(90, 1),
+            environment: Environment::FortnightlyRandomWithdrawal(
+                FortnightlyRandomWithdrawalArgs { seed: 42u128.to_le_bytes(), high: 3, periods: 10 }
+            ),
+            metric: "Target100",
+        });
+
+        assert_eq!(
+            code,
+            "\
+let environment = Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs {
+    seed: 42u128.to_le_bytes(),
+    high: 3,
+    periods: 10,
+});
+let balances = simulate_balance(&interleave(
+    translate_design_InitialAndFortnightlyDeposit((90, 1)),
+    translate_environment(environment),
+));
+let performance = translate_performance_Target100(&balances);
+println!(\"{balances:?} -> {performance}\");
+"
+        );
+    }
+
+    #[test]
+    fn reproduces_the_run() {
+        // What `writes_out_every_setting` expects, pasted in.
+        let environment = Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs {
+            seed: 42u128.to_le_bytes(),
+            high: 3,
+            periods: 10,
+        });
+        let balances = simulate_balance(&interleave(
+            translate_design_InitialAndFortnightlyDeposit((90, 1)),
+            translate_environment(environment),
+        ));
+        let performance = translate_performance_Target100(&balances);
+
+        assert_eq!(
+            Ok((balances, performance)),
+            run_experiment(&ExperimentConfig {
+                design: "InitialAndFortnightlyDeposit",
+                design_parameters: p!(90, 1),
+                environment,
+                metric: "Target100",
+            })
+        );
+    }
+
+    #[test]
+    fn without_an_environment() {
+        assert!(export_run_as_code(&ExperimentConfig::default()).starts_with("let environment = Environment::None;\n"));
+    }
+}
+
+#[cfg(test)]
+mod simulate_balance_constrained_works {
+    use super::*;
+
+    #[test]
+    fn records_excursions_on_both_sides() {
+        let (balances, violations) = simulate_balance_constrained(&[t!(d, 5), t!(d, 10), t!(d, 3), t!(w, 20)], 4, 16);
+
+        assert_eq!(balances, simulate_balance(&[t!(d, 5), t!(d, 10), t!(d, 3), t!(w, 20)]));
+        // 18 is 2 over, and -2 is 6 under.
+        assert_eq!(violations, Violations { count: 2, total_excursion: 8 });
+    }
+
+    #[test]
+    fn ignores_the_initial_balance() {
+        let (_, violations) = simulate_balance_constrained(&[t!(d, 10)], 5, 20);
+
+        assert_eq!(violations, Violations::default());
+    }
+
+    #[test]
+    fn does_not_overflow() {
+        let (_, violations) = simulate_balance_constrained(&[t!(w, Money::MAX); 4], Money::MAX, Money::MAX);
+
+        // The first withdrawal goes down to `-Money::MAX`, then the rest saturate at `Money::MIN`.
+        assert_eq!(violations.total_excursion, 2 * Money::MAX as i64 + 3 * (Money::MAX as i64 - Money::MIN as i64));
+    }
+
+    #[test]
+    fn allows_a_floor_equal_to_the_ceiling() {
+        let (_, violations) = simulate_balance_constrained(&[t!(d, 5), t!(d, 1), t!(w, 1)], 5, 5);
+
+        assert_eq!(violations, Violations { count: 1, total_excursion: 1 });
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_a_floor_above_the_ceiling() {
+        simulate_balance_constrained(&[t!(d, 5)], 10, 0);
+    }
+
+    #[test]
+    fn penalizes_violations() {
+        assert_eq!(translate_performance_ViolationPenalty(Violations { count: 2, total_excursion: 8 }, 1.5, 0.25), 5.);
+        assert_eq!(translate_performance_ViolationPenalty(Violations::default(), 1.5, 0.25), 0.);
+    }
+}
+
+#[cfg(test)]
+mod interleave_works {
+    use super::*;
+
+    #[test]
+    fn keeps_the_design_without_an_environment() {
+        let design = translate_design_FortnightlyDeposit(p!(9));
+
+        assert_eq!(
+            simulate_balance(&interleave(design.clone(), translate_environment(Environment::None))),
+            simulate_balance(&design)
+        );
+    }
+
+    #[test]
+    fn keeps_the_extra_deposit_of_the_initial_and_fortnightly_design() {
+        let transactions = translate_InitialAndFortnightlyDepositAndRandomWithdrawal(p!(90, 1));
+
+        assert_eq!(transactions.len(), 53);
+        assert_eq!(count_transactions(&transactions), 53);
+        let last = transactions[52];
+        assert!(matches!(last.kind, Kind::Deposit));
+        assert_eq!(last.amount, 1);
+    }
+}
+
+#[cfg(test)]
+mod sample_performance_of_design_works {
+    use super::*;
+
+    #[test]
+    fn every_variant_sees_the_same_environment() {
+        let design_variants = (0..16).collect::<Vec<_>>();
+
+        for seed in [<_>::default(), [3; 16], [200; 16]] {
+            let environment = Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() });
+
+            // Drawn once, so every variant is checked against the very same withdrawals.
+            let shared_withdrawals = translate_environment(environment);
+
+            let performances = sample_performance_of_design(translate_design_FortnightlyDeposit, environment, &design_variants);
+
+            for (&m, &performance) in design_variants.iter().zip(&performances) {
+                assert_eq!(
+                    performance,
+                    translate_performance_Target100(&simulate_balance(&interleave(
+                        translate_design_FortnightlyDeposit(p!(m)),
+                        shared_withdrawals.clone(),
+                    )))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn changing_the_seed_shifts_every_variant_by_the_same_withdrawals() {
+        let environment = |seed| Environment::FortnightlyRandomWithdrawal(FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() });
+        let (a, b) = (environment(<_>::default()), environment([3; 16]));
+
+        let performances_a = sample_performance_of_design(translate_design_FortnightlyDeposit, a, &[5, 9, 13]);
+        let performances_b = sample_performance_of_design(translate_design_FortnightlyDeposit, b, &[5, 9, 13]);
+        assert_ne!(performances_a, performances_b);
+
+        // Every variant's balances move by exactly the same amounts when the seed changes, since
+        // the only thing the seed changes is the withdrawals, which are the same for every variant.
+        let shift = |m| {
+            let balances_a = simulate_balance(&interleave(translate_design_FortnightlyDeposit(p!(m)), translate_environment(a)));
+            let balances_b = simulate_balance(&interleave(translate_design_FortnightlyDeposit(p!(m)), translate_environment(b)));
+            balances_a.iter().zip(&balances_b).map(|(a, b)| b - a).collect::<Vec<_>>()
+        };
+        assert_eq!(shift(5), shift(9));
+        assert_eq!(shift(9), shift(13));
+    }
 }
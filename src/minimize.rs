@@ -1,13 +1,16 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::ops::{Index, IndexMut};
 
 type X = f32;
 type Y = f32;
 
-/// The inputs and outputs of a function call.
+/// The inputs and outputs of a function call. The output is usually a single `Y`, but
+/// `minimize_by` allows other outputs.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Call<const N: usize> {
+pub struct Call<const N: usize, V = Y> {
     pub xs: [X; N],
-    pub y: Y,
+    pub y: V,
 }
 
 #[allow(unused)]
@@ -146,6 +149,34 @@ pub fn regular_simplex_centered_at<const N: usize>(
     output
 }
 
+/// A right-angled simplex with its centroid at `center`: one vertex, plus one more vertex `step[i]`
+/// further along each axis `i`. Unlike `regular_simplex_centered_at`, which has one scale shared by
+/// every dimension, each dimension gets its own step here. So this is the better choice when the
+/// parameters have very different scales, such as one ranging over 90 to 115 and another over 0 to
+/// 6, where a regular simplex big enough for the first would be far too big for the second.
+pub fn axis_simplex_centered_at<const N: usize>(
+    center: [X; N],
+    step: [X; N],
+) -> Simplex<N> {
+    let mut output = Simplex {
+        n: [center; N],
+        plus_one: center,
+    };
+
+    for (i, vertex) in output.n.iter_mut().enumerate() {
+        vertex[i] += step[i];
+    }
+
+    // Only one of the vertices is offset along each axis, so the centroid is off by this much.
+    for vertex_index in 0..output.len() {
+        for (x, step) in output[vertex_index].iter_mut().zip(step) {
+            *x -= step / (N + 1) as X;
+        }
+    }
+
+    output
+}
+
 /// Find the minimum of the given function withing the given simplex.
 /// If in doubt of what to use for the simplex, pass
 /// `regular_simplex_centered_at(scale, center)` where `center` is a
@@ -157,6 +188,129 @@ pub fn minimize<const N: usize>(
     // 64k iterations ought to be enough for anybody!
     iters: u16,
 ) -> Call<N> {
+    minimize_with_simplex(f, initial_simplex, iters).best
+}
+
+/// The result of `minimize_with_simplex` or `minimize_by`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Minimized<const N: usize, V = Y> {
+    pub best: Call<N, V>,
+    /// Every vertex of the final simplex, best first. So `simplex[0] == best`.
+    pub simplex: Vec<Call<N, V>>,
+}
+
+impl <const N: usize> Minimized<N> {
+    /// As JSON, in the shape `{"best":{"xs":[...],"y":...},"simplex":[{"xs":[...],"y":...},...]}`.
+    /// Written out by hand, to avoid pulling in a serialization dependency for this one struct.
+    /// Non-finite numbers, which JSON can't represent, come out as `null`.
+    pub fn to_json(&self) -> String {
+        fn number(x: f32) -> String {
+            if x.is_finite() { format!("{x:?}") } else { "null".to_string() }
+        }
+
+        fn call<const N: usize>(call: &Call<N>) -> String {
+            let xs = call.xs.iter().map(|&x| number(x)).collect::<Vec<_>>().join(",");
+            format!("{{\"xs\":[{xs}],\"y\":{}}}", number(call.y))
+        }
+
+        let simplex = self.simplex.iter().map(call).collect::<Vec<_>>().join(",");
+
+        format!("{{\"best\":{},\"simplex\":[{simplex}]}}", call(&self.best))
+    }
+}
+
+/// Like `minimize`, but also returns the whole final simplex, for seeing how it ended up. For
+/// example, whether it collapsed to a point, or got stuck stretched out along a valley.
+pub fn minimize_with_simplex<const N: usize>(
+    f: impl Fn([X; N]) -> Y,
+    initial_simplex: Simplex<N>,
+    iters: u16,
+) -> Minimized<N> {
+    minimize_by(
+        f,
+        |a, b| a.partial_cmp(b).expect("should have no NaNs"),
+        initial_simplex,
+        iters
+    )
+}
+
+/// Orders arrays by their first element, then, among equal first elements, by their second, and
+/// so on. For use as the `compare` argument to `minimize_by`, when some objectives matter
+/// strictly more than others.
+pub fn lexicographic<const K: usize>(a: &[Y; K], b: &[Y; K]) -> Ordering {
+    for i in 0..K {
+        match a[i].partial_cmp(&b[i]).expect("should have no NaNs") {
+            Ordering::Equal => {},
+            ordering => return ordering,
+        }
+    }
+
+    Ordering::Equal
+}
+
+/// Like `minimize_with_simplex`, but `f` can return anything that `compare` can order, with
+/// `Ordering::Less` meaning better. For example, `f` could return several objectives in an array,
+/// with `lexicographic` as the `compare`. Only the ordering of the outputs is ever used, never the
+/// values themselves, so the simplex steps go exactly as they would for a single `Y` that sorted
+/// the same way.
+pub fn minimize_by<const N: usize, V: Copy>(
+    f: impl Fn([X; N]) -> V,
+    compare: impl Fn(&V, &V) -> Ordering,
+    initial_simplex: Simplex<N>,
+    iters: u16,
+) -> Minimized<N, V> {
+    nelder_mead(f, compare, |xs| xs, false, initial_simplex, iters)
+}
+
+/// Like `minimize`, but every point is rounded to the nearest integer in each dimension before
+/// `f` sees it, and the simplex keeps the rounded points as its vertices. For functions that only
+/// depend on the integer part of their inputs anyway, like the performance of a design with whole
+/// dollar amounts, where otherwise the simplex would spend iterations choosing between points that
+/// all give the same design. The returned minimum is always on the integer lattice.
+///
+/// Once the simplex gets down to about one unit across, contracting it moves the trial points less
+/// than half a unit away from the vertices, so they round back onto the vertices, or onto each
+/// other, and the simplex collapses onto fewer distinct points. A collapsed simplex can't search
+/// the directions it collapsed along any more, so this stops as soon as every vertex is on the
+/// same point, and before then can settle on a point that is only better than the lattice points
+/// around it along some directions. So the initial simplex should be at least a few units across
+/// in every dimension, or it may collapse straight away.
+pub fn minimize_on_lattice<const N: usize>(
+    f: impl Fn([X; N]) -> Y,
+    initial_simplex: Simplex<N>,
+    iters: u16,
+) -> Call<N> {
+    nelder_mead(
+        f,
+        |a: &Y, b: &Y| a.partial_cmp(b).expect("should have no NaNs"),
+        |mut xs: [X; N]| {
+            for x in xs.iter_mut() {
+                *x = x.round();
+            }
+            xs
+        },
+        true,
+        initial_simplex,
+        iters
+    ).best
+}
+
+/// The shared implementation of the `minimize` functions. Every point is passed through `snap`
+/// before being evaluated or becoming a vertex. With `stop_when_collapsed`, this stops early once
+/// every vertex is on the same point, which only a `snap` onto a lattice makes likely.
+fn nelder_mead<const N: usize, V: Copy>(
+    f: impl Fn([X; N]) -> V,
+    compare: impl Fn(&V, &V) -> Ordering,
+    snap: impl Fn([X; N]) -> [X; N],
+    stop_when_collapsed: bool,
+    initial_simplex: Simplex<N>,
+    iters: u16,
+) -> Minimized<N, V> {
+    let lt = |a: &V, b: &V| compare(a, b) == Ordering::Less;
+    let le = |a: &V, b: &V| compare(a, b) != Ordering::Greater;
+    let gt = |a: &V, b: &V| compare(a, b) == Ordering::Greater;
+    let ge = |a: &V, b: &V| compare(a, b) != Ordering::Less;
+
     // Nelder–Mead method
     // References used:
     // Wikipedia Article: https://en.wikipedia.org/wiki/Nelder%E2%80%93Mead_method
@@ -173,14 +327,20 @@ pub fn minimize<const N: usize>(
     // TODO? Do this on the stack?
     let mut s = Vec::with_capacity(N + 1);
     for i in 0..(N + 1) {
-        let xs = initial_simplex[i];
+        let xs = snap(initial_simplex[i]);
         s.push(Call { xs, y: f(xs) });
     }
 
 
     while k < iters {
+        // Once every vertex is on the same point, every trial point is there too, so nothing
+        // would change any more.
+        if stop_when_collapsed && s.iter().all(|call| call.xs == s[0].xs) {
+            break
+        }
+
         // Order
-        s.sort_by(|a, b| a.y.partial_cmp(&b.y).expect("should have no NaNs"));
+        s.sort_by(|a, b| compare(&a.y, &b.y));
 
         let l_k = 0;
         let h_k = s.len() - 1;
@@ -192,19 +352,20 @@ pub fn minimize<const N: usize>(
 
         let x_h_k = s[h_k].xs;
 
+        // The centroid of every vertex except the worst one.
         let x_c = {
             let mut sum = [0.; N];
 
-            for call in s.iter() {
-                for i in 0..N {
-                    sum[i] += call.xs[i];
+            for call in s[..h_k].iter() {
+                for (total, x) in sum.iter_mut().zip(call.xs) {
+                    *total += x;
                 }
             }
 
             let scale = 1. / (N as X);
 
-            for i in 0..N {
-                sum[i] *= scale;
+            for total in sum.iter_mut() {
+                *total *= scale;
             }
 
             sum
@@ -216,14 +377,14 @@ pub fn minimize<const N: usize>(
                 output[i] *= 1. + alpha;
                 output[i] -= alpha * x_h_k[i];
             }
-            output
+            snap(output)
         };
 
         // Reflect
         let x_r = x_super_k(ALPHA);
         let f_r = f(x_r);
 
-        if f_1 <= f_r && f_r < f_n {
+        if le(&f_1, &f_r) && lt(&f_r, &f_n) {
             s[h_k] = Call { xs: x_r, y: f_r };
         }
 
@@ -231,9 +392,9 @@ pub fn minimize<const N: usize>(
         let x_e = x_super_k(GAMMA);
         let f_e = f(x_e);
 
-        if f_r < f_1 && f_e < f_r {
+        if lt(&f_r, &f_1) && lt(&f_e, &f_r) {
             s[h_k] = Call { xs: x_e, y: f_e };
-        } else if f_r < f_1 && f_r <= f_e {
+        } else if lt(&f_r, &f_1) && le(&f_r, &f_e) {
             s[h_k] = Call { xs: x_r, y: f_r };
         }
 
@@ -241,7 +402,7 @@ pub fn minimize<const N: usize>(
         let x_oc = x_super_k(RHO);
         let f_oc = f(x_oc);
 
-        if f_n <= f_r && f_r < f_n_1 && f_oc <= f_r {
+        if le(&f_n, &f_r) && lt(&f_r, &f_n_1) && le(&f_oc, &f_r) {
             s[h_k] = Call { xs: x_oc, y: f_oc };
         }
 
@@ -249,26 +410,137 @@ pub fn minimize<const N: usize>(
         let x_ic = x_super_k(SIGMA);
         let f_ic = f(x_ic);
 
-        if f_r >= f_n_1 && f_ic < f_n_1 && f_oc <= f_r {
+        if ge(&f_r, &f_n_1) && lt(&f_ic, &f_n_1) && le(&f_oc, &f_r) {
             s[h_k] = Call { xs: x_ic, y: f_ic };
         }
 
         // Shrink
-        if (f_n <= f_r && f_r < f_n_1 && f_oc > f_r) || if f_r < f_ic { f_r } else { /* NaN ends up here. Hopefully that case doesn't happen! */ f_ic }  >= f_n_1 {
-            for i in 0..s.len() {
+        if (le(&f_n, &f_r) && lt(&f_r, &f_n_1) && gt(&f_oc, &f_r)) || ge(if lt(&f_r, &f_ic) { &f_r } else { &f_ic }, &f_n_1) {
+            for vertex in s.iter_mut() {
                 let mut xs = x_1;
-                for j in 0..N {
-                    xs[j] += s[i].xs[j];
-                    xs[j] *= 0.5;
+                for (x, vertex_x) in xs.iter_mut().zip(vertex.xs) {
+                    *x += vertex_x;
+                    *x *= 0.5;
                 }
-                s[i] = Call { xs, y: f(xs) };
+                let xs = snap(xs);
+                *vertex = Call { xs, y: f(xs) };
             }
         }
 
         k += 1;
     }
 
-    s[0]
+    // The last iteration may have replaced a vertex with one better than the previous best.
+    s.sort_by(|a, b| compare(&a.y, &b.y));
+
+    Minimized {
+        best: s[0],
+        simplex: s,
+    }
+}
+
+/// Like `minimize`, but works in a space where each dimension `i` is measured in units of
+/// `scales[i]`, converting back before each call to `f`. The initial simplex is a regular simplex
+/// of unit scale in that space, centered at `center`, so it spans roughly `scales[i]` along each
+/// dimension `i`. Useful when the parameters have very different scales, where a regular simplex
+/// in the original space is either too big for the small parameters, or too small for the big
+/// ones. `center`, the returned minimum, and what `f` gets passed are all in the original units.
+pub fn minimize_scaled<const N: usize>(
+    f: impl Fn([X; N]) -> Y,
+    center: [X; N],
+    scales: [X; N],
+    iters: u16,
+) -> Call<N> {
+    let mut normalized_center = center;
+    for i in 0..N {
+        normalized_center[i] /= scales[i];
+    }
+
+    let denormalize = |mut xs: [X; N]| {
+        for i in 0..N {
+            xs[i] *= scales[i];
+        }
+        xs
+    };
+
+    let normalized_minimum = minimize(
+        |xs| f(denormalize(xs)),
+        regular_simplex_centered_at(1.0, normalized_center),
+        iters
+    );
+
+    Call {
+        xs: denormalize(normalized_minimum.xs),
+        y: normalized_minimum.y,
+    }
+}
+
+/// Runs `minimize` once from each of `starts`, each time with a regular simplex of the given
+/// `scale` centered there, and returns the best of the minimums found. For functions with more than
+/// one basin, where a single run can settle in whichever basin it started nearest.
+pub fn minimize_multistart<const N: usize>(
+    f: impl Fn([X; N]) -> Y,
+    starts: &[[X; N]],
+    scale: X,
+    iters: u16,
+) -> Option<Call<N>> {
+    starts
+        .iter()
+        .map(|&start| minimize(&f, regular_simplex_centered_at(scale, start), iters))
+        .min_by(|a, b| a.y.partial_cmp(&b.y).expect("should have no NaNs"))
+}
+
+/// Like `minimize`, but also returns every call made to `f`, in the order they were made, for
+/// seeing where the simplex went on the way to the minimum.
+pub fn minimize_recording<const N: usize>(
+    f: impl Fn([X; N]) -> Y,
+    initial_simplex: Simplex<N>,
+    iters: u16,
+) -> (Call<N>, Vec<Call<N>>) {
+    let calls = RefCell::new(Vec::new());
+
+    let minimum = minimize(
+        |xs| {
+            let y = f(xs);
+            calls.borrow_mut().push(Call { xs, y });
+            y
+        },
+        initial_simplex,
+        iters
+    );
+
+    (minimum, calls.into_inner())
+}
+
+#[cfg(test)]
+mod axis_simplex_centered_at_works {
+    use super::*;
+
+    macro_rules! approx_eq {
+        ($a: expr, $b: expr) => {
+            assert!(($a - $b).abs() < 0.0001);
+        };
+    }
+
+    #[test]
+    fn in_2d() {
+        let output = axis_simplex_centered_at([100., 3.], [10., 2.]);
+
+        let mut centroid = [0.; 2];
+        for vertex_index in 0..output.len() {
+            for i in 0..2 {
+                centroid[i] += output[vertex_index][i] / output.len() as X;
+            }
+        }
+        approx_eq!(centroid[0], 100.);
+        approx_eq!(centroid[1], 3.);
+
+        // Each of the first `N` vertices is one step along its own axis from the last one.
+        approx_eq!(output[0][0] - output.plus_one[0], 10.);
+        approx_eq!(output[0][1] - output.plus_one[1], 0.);
+        approx_eq!(output[1][0] - output.plus_one[0], 0.);
+        approx_eq!(output[1][1] - output.plus_one[1], 2.);
+    }
 }
 
 #[cfg(test)]
@@ -295,4 +567,166 @@ mod minimize_works {
             TWO_D_ZERO,
         );
     }
+
+    #[test]
+    fn on_a_2d_bowl() {
+        // With the centroid taken over every vertex, the worst one included, as it once was, the
+        // simplex stalls well short of the minimum here, since each reflection is pulled back
+        // towards the very vertex it is meant to move away from.
+        let minimum = minimize(|[x, y]: [X; 2]| x * x + y * y, regular_simplex_centered_at(3.0, [2.0, 1.0]), 100);
+
+        assert!(minimum.xs[0].abs() < 0.001 && minimum.xs[1].abs() < 0.001, "{:?}", minimum);
+    }
+
+    #[test]
+    fn scaled_on_a_badly_scaled_bowl() {
+        let f = |[x, y]: [X; 2]| {
+            let dx = (x - 1000.) / 100.;
+            let dy = (y - 0.5) * 10.;
+            dx * dx + dy * dy
+        };
+
+        let minimum = minimize_scaled(f, [900., 0.7], [100., 0.1], 100);
+
+        assert!((minimum.xs[0] - 1000.).abs() < 0.1, "{:?}", minimum);
+        assert!((minimum.xs[1] - 0.5).abs() < 0.001, "{:?}", minimum);
+        assert_eq!(minimum.y, f(minimum.xs));
+    }
+
+    #[test]
+    fn multistart_finds_the_deeper_basin() {
+        // A shallow basin at -2 and a deeper one at 3.
+        let f = |[x]: [X; 1]| ((x + 2.) * (x + 2.)).min((x - 3.) * (x - 3.) - 1.);
+
+        let from_the_left = minimize(f, regular_simplex_centered_at(1.0, [-3.0]), 100);
+        assert!((from_the_left.xs[0] + 2.).abs() < 0.01);
+
+        let best = minimize_multistart(f, &[[-3.0], [0.5], [4.0]], 1.0, 100).unwrap();
+        assert!((best.xs[0] - 3.).abs() < 0.01);
+        assert!((best.y + 1.).abs() < 0.001);
+
+        assert_eq!(minimize_multistart(f, &[], 1.0, 100), None);
+    }
+
+    #[test]
+    fn with_simplex_on_x_squared() {
+        let minimized = minimize_with_simplex::<1>(|[x]| x * x, regular_simplex_centered_at(4.0, [-2.0]), 100);
+
+        assert_eq!(minimized.best, TWO_D_ZERO);
+        assert_eq!(minimized.simplex.len(), 2);
+        assert_eq!(minimized.simplex[0], minimized.best);
+        assert!(minimized.simplex[0].y <= minimized.simplex[1].y);
+    }
+
+    #[test]
+    fn returns_the_best_vertex_after_the_last_iteration() {
+        // The one iteration expands the simplex from 11 through 10 out to 8, which beats 10, the best
+        // vertex going into it. Without sorting after it, 10 would come back as the minimum.
+        let simplex = Simplex { n: [[11.]], plus_one: [10.] };
+
+        assert_eq!(minimize::<1>(|[x]| x * x, simplex, 1), Call { xs: [8.], y: 64. });
+    }
+
+    #[test]
+    fn minimized_to_json() {
+        let minimized = Minimized {
+            best: Call { xs: [1.5, -2.0], y: 0.25 },
+            simplex: vec![
+                Call { xs: [1.5, -2.0], y: 0.25 },
+                Call { xs: [1.0, 0.0], y: 1.0 },
+                Call { xs: [3.0, 1.0], y: f32::INFINITY },
+            ],
+        };
+
+        assert_eq!(
+            minimized.to_json(),
+            r#"{"best":{"xs":[1.5,-2.0],"y":0.25},"simplex":[{"xs":[1.5,-2.0],"y":0.25},{"xs":[1.0,0.0],"y":1.0},{"xs":[3.0,1.0],"y":null}]}"#
+        );
+    }
+
+    #[test]
+    fn on_lattice_only_evaluates_integers() {
+        let f = |[x, y]: [X; 2]| {
+            assert_eq!(x, x.round());
+            assert_eq!(y, y.round());
+            (x - 3.3) * (x - 3.3) + (y + 1.8) * (y + 1.8)
+        };
+
+        let minimum = minimize_on_lattice(f, axis_simplex_centered_at([10., 10.], [4., 4.]), 100);
+
+        assert_eq!(minimum.xs, [3., -2.]);
+    }
+
+    #[test]
+    fn on_lattice_stops_once_collapsed() {
+        let calls = RefCell::new(0);
+
+        // Too small to span more than one lattice point.
+        let minimum = minimize_on_lattice(
+            |[x]| { *calls.borrow_mut() += 1; x * x },
+            axis_simplex_centered_at([5.], [0.1]),
+            100
+        );
+
+        assert_eq!(minimum.xs, [5.]);
+        assert_eq!(calls.into_inner(), 2);
+    }
+
+    #[test]
+    fn off_lattice_keeps_going_when_collapsed() {
+        let calls = RefCell::new(0);
+
+        let minimum = minimize(
+            |[x]| { *calls.borrow_mut() += 1; x * x },
+            Simplex { n: [[5.]], plus_one: [5.] },
+            10
+        );
+
+        assert_eq!(minimum.xs, [5.]);
+        // Every iteration still runs: the four trial points, then a shrink re-evaluating both
+        // vertices, since none of the trial points beat them.
+        assert_eq!(calls.into_inner(), 2 + 10 * 6);
+    }
+
+    #[test]
+    fn by_lexicographic_order() {
+        // Anywhere in -2..=2 is equally good by the first objective, so the second decides.
+        let first = |x: X| (x.abs() - 2.).max(0.);
+
+        let inside = minimize_by::<1, [Y; 2]>(
+            |[x]| [first(x), (x - 1.5) * (x - 1.5)],
+            lexicographic,
+            regular_simplex_centered_at(4.0, [-3.0]),
+            100
+        );
+        assert!((inside.best.xs[0] - 1.5).abs() < 0.01, "{:?}", inside.best);
+
+        // Here the second objective would rather be at 3, but the first one comes first.
+        let at_the_edge = minimize_by::<1, [Y; 2]>(
+            |[x]| [first(x), (x - 3.) * (x - 3.)],
+            lexicographic,
+            regular_simplex_centered_at(4.0, [-3.0]),
+            100
+        );
+        assert!((at_the_edge.best.xs[0] - 2.).abs() < 0.01, "{:?}", at_the_edge.best);
+    }
+
+    #[test]
+    fn by_matches_minimize_on_a_single_objective() {
+        let f = |[x, y]: [X; 2]| (x - 1.) * (x - 1.) + (y + 2.) * (y + 2.);
+
+        assert_eq!(
+            minimize_by(f, |a: &Y, b: &Y| a.partial_cmp(b).unwrap(), regular_simplex_centered_at(3.0, [0.0, 0.0]), 50).best,
+            minimize(f, regular_simplex_centered_at(3.0, [0.0, 0.0]), 50),
+        );
+    }
+
+    #[test]
+    fn recording_on_x_squared() {
+        let (minimum, calls) = minimize_recording::<1>(|[x]| x * x, regular_simplex_centered_at(4.0, [-2.0]), 100);
+
+        assert_eq!(minimum, minimize::<1>(|[x]| x * x, regular_simplex_centered_at(4.0, [-2.0]), 100));
+        assert!(calls.contains(&minimum));
+        assert!(calls.iter().all(|call| call.y == call.xs[0] * call.xs[0]));
+    }
 }
\ No newline at end of file
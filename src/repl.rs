@@ -0,0 +1,196 @@
+use crate::{
+    export_run_as_code,
+    format_performance,
+    run_experiment,
+    sparkline,
+    Environment,
+    ExperimentConfig,
+    FortnightlyRandomWithdrawalArgs,
+    Money,
+    DESIGNS,
+    METRICS,
+    PRECISION,
+};
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+commands:
+    design <name>         pick a design, one of the ones listed by `designs`
+    params <a> [b]        set the design parameters
+    env none              run the design on its own
+    env random [high]     interleave withdrawals drawn from 0..high, (5 by default)
+    seed <n>              seed the environment's randomness with the number n, (kept for a
+                          later `env random` if the environment is `none`)
+    metric <name>         pick a metric, one of the ones listed by `metrics`
+    designs, metrics      list the names that can be picked
+    show                  print the current settings
+    run                   print the balances and their performance
+    export                print Rust code that reproduces `run`
+    help                  print this
+    quit                  leave";
+
+/// Reads commands from `input` one line at a time, each adjusting the experiment or running it,
+/// until `quit` or the end of `input`, writing the results, and a prompt before each command, to
+/// `output`. Unrecognized commands are reported, and otherwise ignored.
+pub fn run(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut config = ExperimentConfig::default();
+    // Kept apart from `config.environment`, since `Environment::None` has nowhere to hold it.
+    let mut seed = crate::xs::Seed::default();
+
+    writeln!(output, "type `help` for the commands")?;
+    write!(output, "> ")?;
+    output.flush()?;
+
+    for line in input.lines() {
+        let line = line?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words[..] {
+            [] => {},
+            ["quit"] | ["exit"] => break,
+            ["help"] => writeln!(output, "{HELP}")?,
+            ["designs"] => for (name, _) in DESIGNS {
+                writeln!(output, "{name}")?;
+            },
+            ["metrics"] => for (name, _) in METRICS {
+                writeln!(output, "{name}")?;
+            },
+            ["design", name] => match DESIGNS.iter().find(|(n, _)| *n == name) {
+                Some((n, _)) => config.design = n,
+                None => writeln!(output, "no design named {name}; try `designs`")?,
+            },
+            ["metric", name] => match METRICS.iter().find(|(n, _)| *n == name) {
+                Some((n, _)) => config.metric = n,
+                None => writeln!(output, "no metric named {name}; try `metrics`")?,
+            },
+            ["params", a] => match a.parse::<Money>() {
+                Ok(a) => config.design_parameters = (a, 0),
+                Err(_) => writeln!(output, "expected a whole number of dollars")?,
+            },
+            ["params", a, b] => match (a.parse::<Money>(), b.parse::<Money>()) {
+                (Ok(a), Ok(b)) => config.design_parameters = (a, b),
+                _ => writeln!(output, "expected whole numbers of dollars")?,
+            },
+            ["env", "none"] => config.environment = Environment::None,
+            ["env", "random"] => config.environment = Environment::FortnightlyRandomWithdrawal(
+                FortnightlyRandomWithdrawalArgs { seed, ..<_>::default() }
+            ),
+            ["env", "random", high] => match high.parse::<u32>() {
+                Ok(0) => writeln!(output, "high must be at least 1, since withdrawals are drawn from 0..high")?,
+                Ok(high) => config.environment = Environment::FortnightlyRandomWithdrawal(
+                    FortnightlyRandomWithdrawalArgs { seed, high, ..<_>::default() }
+                ),
+                Err(_) => writeln!(output, "expected a whole number for high")?,
+            },
+            ["seed", n] => match n.parse::<u128>() {
+                Ok(n) => {
+                    seed = n.to_le_bytes();
+                    config.environment = config.environment.with_seed(seed);
+                },
+                Err(_) => writeln!(output, "expected a whole number for the seed")?,
+            },
+            ["show"] => show(&mut output, &config)?,
+            ["export"] => write!(output, "{}", export_run_as_code(&config))?,
+            ["run"] => match run_experiment(&config) {
+                Ok((balances, performance)) => {
+                    writeln!(output, "{balances:?}")?;
+                    writeln!(output, "{}", sparkline(&balances))?;
+                    writeln!(output, "{}: {}", config.metric, format_performance(performance, PRECISION))?;
+                },
+                Err(error) => writeln!(output, "cannot run these settings: {error}")?,
+            },
+            _ => writeln!(output, "unrecognized command: {line}; try `help`")?,
+        }
+
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+
+    writeln!(output)
+}
+
+fn show(output: &mut impl Write, config: &ExperimentConfig) -> io::Result<()> {
+    writeln!(output, "design: {} {:?}", config.design, config.design_parameters)?;
+    match config.environment {
+        Environment::None => writeln!(output, "environment: none")?,
+        Environment::FortnightlyRandomWithdrawal(args) => writeln!(
+            output,
+            "environment: random withdrawals from 0..{} for {} periods, seed {}",
+            args.high,
+            args.periods,
+            u128::from_le_bytes(args.seed)
+        )?,
+    }
+    writeln!(output, "metric: {}", config.metric)
+}
+
+#[cfg(test)]
+mod run_works {
+    use super::*;
+
+    fn run_on(input: &str) -> String {
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn runs_the_default_experiment() {
+        let output = run_on("run\n");
+
+        let (balances, _) = run_experiment(&ExperimentConfig::default()).unwrap();
+        assert!(output.contains(&format!("{balances:?}")), "{output}");
+        assert!(output.contains("Target100: "), "{output}");
+    }
+
+    #[test]
+    fn applies_settings_before_running() {
+        let output = run_on("design InitialAndFortnightlyDeposit\nparams 90 1\nenv random 3\nseed 42\nshow\nrun\nquit\nrun\n");
+
+        assert!(output.contains("design: InitialAndFortnightlyDeposit (90, 1)"), "{output}");
+        assert!(output.contains("random withdrawals from 0..3 for 26 periods, seed 42"), "{output}");
+
+        let (balances, _) = run_experiment(&ExperimentConfig {
+            design: "InitialAndFortnightlyDeposit",
+            design_parameters: (90, 1),
+            environment: Environment::FortnightlyRandomWithdrawal(
+                FortnightlyRandomWithdrawalArgs { seed: 42u128.to_le_bytes(), high: 3, ..<_>::default() }
+            ),
+            metric: "Target100",
+        }).unwrap();
+        // Only once, since nothing after `quit` runs.
+        assert_eq!(output.matches(&format!("{balances:?}")).count(), 1, "{output}");
+    }
+
+    #[test]
+    fn reports_bad_commands() {
+        let output = run_on("design Nope\nparams x\nfrobnicate\n");
+
+        assert!(output.contains("no design named Nope"), "{output}");
+        assert!(output.contains("expected a whole number of dollars"), "{output}");
+        assert!(output.contains("unrecognized command: frobnicate"), "{output}");
+    }
+
+    #[test]
+    fn rejects_an_empty_withdrawal_range() {
+        let output = run_on("env random 0\nshow\nrun\n");
+
+        assert!(output.contains("high must be at least 1"), "{output}");
+        assert!(output.contains("environment: none"), "{output}");
+    }
+
+    #[test]
+    fn keeps_the_seed_for_a_later_environment() {
+        let output = run_on("seed 42\nenv random\nshow\n");
+
+        assert!(output.contains("seed 42"), "{output}");
+    }
+
+    #[test]
+    fn refuses_to_run_negative_deposits() {
+        let output = run_on("params -5\nrun\n");
+
+        assert!(output.contains("cannot run these settings: transaction 0 has a negative amount"), "{output}");
+        assert!(!output.contains("Target100: "), "{output}");
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    interleave,
+    simulate_balance,
+    translate_environment,
+    DesignParameters,
+    DesignTranslator,
+    Environment,
+    FortnightlyRandomWithdrawalArgs,
+    Money,
+};
+use crate::xs::Seed;
+
+/// A requirement that a savings plan's balances should meet.
+#[derive(Clone, Copy, Debug)]
+pub enum Constraint {
+    /// The final balance is at least this much.
+    FinalBalanceAtLeast(Money),
+    /// The balance is never below this, including before anything happens.
+    NeverBelow(Money),
+    /// Once the first `after_transactions` transactions have happened, the balance stays within
+    /// `tolerance` of `target`. Transactions are counted from the combined list, so with the design
+    /// and environment interleaved, a fortnight is two of them.
+    WithinOfTargetAfter { target: Money, tolerance: Money, after_transactions: usize },
+}
+
+impl Constraint {
+    fn check(self, balances: &[Money]) -> bool {
+        use Constraint::*;
+        match self {
+            FinalBalanceAtLeast(minimum) => balances.last().is_some_and(|&b| b >= minimum),
+            NeverBelow(minimum) => balances.iter().all(|&b| b >= minimum),
+            WithinOfTargetAfter { target, tolerance, after_transactions } => balances
+                .iter()
+                .skip(after_transactions)
+                // In i64, since the distance between two `Money`s can be more than a `Money` holds.
+                .all(|&b| (b as i64 - target as i64).abs() <= tolerance as i64),
+        }
+    }
+}
+
+/// Whether each constraint of a spec passed, in the order they were given.
+#[derive(Debug)]
+pub struct SpecReport {
+    pub results: Vec<(Constraint, bool)>,
+}
+
+impl SpecReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|&(_, passed)| passed)
+    }
+}
+
+/// Replays the design against the random withdrawal environment with the given seed, and checks
+/// the resulting balances against every constraint in `spec`.
+pub fn check_spec(
+    design_translator: DesignTranslator,
+    design_parameters: DesignParameters,
+    env_seed: Seed,
+    spec: &[Constraint],
+) -> SpecReport {
+    let balances = simulate_balance(&interleave(
+        design_translator(design_parameters),
+        translate_environment(Environment::FortnightlyRandomWithdrawal(
+            FortnightlyRandomWithdrawalArgs { seed: env_seed, ..<_>::default() }
+        )),
+    ));
+
+    SpecReport {
+        results: spec
+            .iter()
+            .map(|&constraint| (constraint, constraint.check(&balances)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod check_spec_works {
+    use super::*;
+    use crate::translate_design_FortnightlyDeposit;
+
+    #[test]
+    fn on_design_1() {
+        // With the default seed, design_1 ends on 178, and never dips below 0.
+        let report = check_spec(
+            translate_design_FortnightlyDeposit,
+            (9, 0),
+            <_>::default(),
+            &[
+                Constraint::FinalBalanceAtLeast(178),
+                Constraint::FinalBalanceAtLeast(179),
+                Constraint::NeverBelow(0),
+                Constraint::WithinOfTargetAfter { target: 100, tolerance: 50, after_transactions: 10 },
+            ],
+        );
+
+        assert_eq!(
+            report.results.iter().map(|&(_, passed)| passed).collect::<Vec<_>>(),
+            [true, false, true, false]
+        );
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn within_target_only_looks_after_the_given_transactions() {
+        let in_range = Constraint::WithinOfTargetAfter { target: 100, tolerance: 5, after_transactions: 2 };
+
+        assert!(in_range.check(&[0, 50, 98, 103, 100]));
+        assert!(!in_range.check(&[0, 50, 94, 103, 100]));
+    }
+
+    #[test]
+    fn within_target_handles_extreme_balances() {
+        let near_max = Constraint::WithinOfTargetAfter { target: Money::MAX, tolerance: 1, after_transactions: 0 };
+        let near_min = Constraint::WithinOfTargetAfter { target: Money::MIN, tolerance: Money::MAX, after_transactions: 0 };
+
+        assert!(near_max.check(&[Money::MAX - 1, Money::MAX]));
+        assert!(!near_max.check(&[Money::MAX, Money::MIN]));
+        assert!(near_min.check(&[Money::MIN, -1]));
+        assert!(!near_min.check(&[Money::MIN, 0]));
+    }
+}
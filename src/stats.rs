@@ -0,0 +1,330 @@
+use crate::xs;
+
+/// The arithmetic mean. `NaN` for an empty slice.
+pub fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+/// The population variance, that is dividing by `n` rather than `n - 1`. `NaN` for an empty slice.
+pub fn variance(values: &[f32]) -> f32 {
+    let mean = mean(values);
+
+    values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / values.len() as f32
+}
+
+/// The sample variance, that is dividing by `n - 1` rather than `n`, as is right when estimating
+/// the variance of a larger population from `values`. `NaN` for fewer than two values.
+pub fn sample_variance(values: &[f32]) -> f32 {
+    let mean = mean(values);
+
+    values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / (values.len() as f32 - 1.)
+}
+
+/// The estimated standard error of the mean of `values`.
+#[allow(unused)]
+pub fn standard_error(values: &[f32]) -> f32 {
+    (sample_variance(values) / values.len() as f32).sqrt()
+}
+
+/// Kish's effective sample size: how many equally weighted samples would give an estimate as
+/// precise as the weighted mean of samples with these `weights`. Equal weights give back the
+/// count, and the more uneven the weights the fewer samples are effectively contributing.
+/// `NaN` if the weights are empty or all zero.
+pub fn effective_sample_size(weights: &[f32]) -> f32 {
+    let sum = weights.iter().sum::<f32>();
+    let sum_of_squares = weights.iter().map(|w| w * w).sum::<f32>();
+
+    sum * sum / sum_of_squares
+}
+
+/// The exact median, found by selection rather than a full sort. The mean of the middle two for an
+/// even number of values. `NaN` for an empty slice.
+pub fn median(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return f32::NAN
+    }
+
+    let len = values.len();
+    let mut values = values.to_vec();
+    let compare = |a: &f32, b: &f32| a.partial_cmp(b).expect("should have no NaNs");
+
+    let (lower, &mut upper_middle, _) = values.select_nth_unstable_by(len / 2, compare);
+    if len % 2 == 1 {
+        upper_middle
+    } else {
+        let lower_middle = lower.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        (lower_middle + upper_middle) / 2.
+    }
+}
+
+/// An estimate of the `p` quantile of a stream of values, (0.5 for the median,) using the P-square
+/// algorithm of Jain and Chlamtac, which keeps only five markers rather than every value. So it
+/// suits very long or streamed series, like those from `simulate_balance_streaming`, where
+/// storing every balance for an exact `median` would take too much memory.
+///
+/// Before the fifth value, the estimate is the exact quantile of the values seen so far,
+/// interpolating linearly between the two nearest when it falls between them, so for the median it
+/// matches `median`. From the fifth value on it is an approximation, and there is no hard bound on
+/// how far it can be from the exact quantile: the markers are adjusted by fitting a parabola
+/// through their neighbours, which assumes the distribution is smooth around the quantile. For
+/// smooth, unimodal distributions with the values arriving in no particular order, the error is
+/// typically a small fraction of the spread of the values once a few hundred have been seen. For
+/// values with gaps or clumps near the quantile it is worse, and sorted or trending input, such as
+/// a steadily growing balance, is the worst case, as the markers can only move one position per
+/// value and so lag behind.
+#[derive(Clone, Debug)]
+pub struct PSquare {
+    p: f32,
+    count: usize,
+    heights: [f32; 5],
+    positions: [f32; 5],
+    desired_positions: [f32; 5],
+    increments: [f32; 5],
+}
+
+impl PSquare {
+    pub fn new(p: f32) -> Self {
+        assert!((0. ..=1.).contains(&p));
+
+        Self {
+            p,
+            count: 0,
+            heights: [0.; 5],
+            positions: [1., 2., 3., 4., 5.],
+            desired_positions: [1., 1. + 2. * p, 1. + 4. * p, 3. + 2. * p, 5.],
+            increments: [0., p / 2., p, (1. + p) / 2., 1.],
+        }
+    }
+
+    pub fn observe(&mut self, x: f32) {
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(|a, b| a.partial_cmp(b).expect("should have no NaNs"));
+            }
+            return
+        }
+        self.count += 1;
+
+        let q = &mut self.heights;
+        let n = &mut self.positions;
+
+        // Find the cell `x` falls in, stretching the outer markers to fit it if needed.
+        let k = if x < q[0] {
+            q[0] = x;
+            0
+        } else if x >= q[4] {
+            q[4] = x;
+            3
+        } else {
+            (0..4).rfind(|&i| q[i] <= x).unwrap_or(0)
+        };
+
+        for position in n[k + 1..].iter_mut() {
+            *position += 1.;
+        }
+        for (desired, increment) in self.desired_positions.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        // Move the middle markers towards where they should be, if they can move without landing
+        // on a neighbour.
+        for i in 1..4 {
+            let d = self.desired_positions[i] - n[i];
+            if (d >= 1. && n[i + 1] - n[i] > 1.) || (d <= -1. && n[i - 1] - n[i] < -1.) {
+                let d = d.signum();
+
+                let parabolic = q[i] + d / (n[i + 1] - n[i - 1]) * (
+                    (n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                    + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1])
+                );
+
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0. { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    /// The number of values observed so far.
+    #[allow(unused)]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The current estimate of the quantile. `None` before any values have been observed.
+    pub fn estimate(&self) -> Option<f32> {
+        match self.count {
+            0 => None,
+            count if count < 5 => {
+                let mut seen = self.heights[..count].to_vec();
+                seen.sort_by(|a, b| a.partial_cmp(b).expect("should have no NaNs"));
+                let position = self.p * (count - 1) as f32;
+                let (below, above) = (position.floor() as usize, position.ceil() as usize);
+                Some(seen[below] + (position - below as f32) * (seen[above] - seen[below]))
+            },
+            _ => Some(self.heights[2]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod effective_sample_size_works {
+    use super::*;
+
+    #[test]
+    fn on_equal_weights() {
+        assert_eq!(effective_sample_size(&[0.25; 4]), 4.);
+        assert_eq!(effective_sample_size(&[3.; 5]), 5.);
+    }
+
+    #[test]
+    fn on_uneven_weights() {
+        // (0.8 + 0.2)² / (0.64 + 0.04)
+        assert!((effective_sample_size(&[0.8, 0.2]) - 1. / 0.68).abs() < 1e-5);
+        assert_eq!(effective_sample_size(&[1., 0., 0.]), 1.);
+    }
+}
+
+/// A percentile bootstrap confidence interval for the mean of `performances`: the mean of
+/// `n_resamples` resamples, each drawn with replacement, and then the middle `level` fraction of
+/// those means. Unlike `mean ± z * standard_error` this does not assume the means are normally
+/// distributed, so it holds up better on skewed performances. Returns `(low, high)`.
+pub fn bootstrap_ci(
+    performances: &[f32],
+    n_resamples: usize,
+    level: f32,
+    seed: xs::Seed,
+) -> (f32, f32) {
+    assert!(!performances.is_empty());
+    assert!(n_resamples > 0);
+
+    let mut rng = xs::from_seed(seed);
+    let mut resample = vec![0.; performances.len()];
+
+    let mut means = Vec::with_capacity(n_resamples);
+    for _ in 0..n_resamples {
+        for r in resample.iter_mut() {
+            *r = performances[xs::range(&mut rng, 0..performances.len() as u32) as usize];
+        }
+        means.push(mean(&resample));
+    }
+
+    means.sort_by(|a, b| a.partial_cmp(b).expect("should have no NaNs"));
+
+    let tail = (1. - level) / 2.;
+    let last = n_resamples - 1;
+    let index_of = |fraction: f32| ((fraction * last as f32).round() as usize).min(last);
+
+    (means[index_of(tail)], means[index_of(1. - tail)])
+}
+
+#[cfg(test)]
+mod bootstrap_ci_works {
+    use super::*;
+
+    #[test]
+    fn on_constant_performances() {
+        assert_eq!(bootstrap_ci(&[3.; 10], 100, 0.95, <_>::default()), (3., 3.));
+    }
+
+    #[test]
+    fn contains_the_mean_and_narrows_with_level() {
+        let performances = [1., 2., 2., 3., 5., 8., 13., 21.];
+        let m = mean(&performances);
+
+        let wide = bootstrap_ci(&performances, 1000, 0.95, <_>::default());
+        let narrow = bootstrap_ci(&performances, 1000, 0.5, <_>::default());
+
+        assert!(wide.0 < m && m < wide.1, "{wide:?}");
+        assert!(wide.0 <= narrow.0 && narrow.1 <= wide.1, "{wide:?} {narrow:?}");
+        assert!(wide.0 >= 1. && wide.1 <= 21.);
+    }
+}
+
+#[cfg(test)]
+mod median_works {
+    use super::*;
+
+    #[test]
+    fn on_odd_and_even_lengths() {
+        assert!(median(&[]).is_nan());
+        assert_eq!(median(&[4.]), 4.);
+        assert_eq!(median(&[5., 1., 3.]), 3.);
+        assert_eq!(median(&[5., 1., 3., 2.]), 2.5);
+    }
+}
+
+#[cfg(test)]
+mod p_square_works {
+    use super::*;
+
+    #[test]
+    fn is_exact_for_the_first_five() {
+        let mut estimator = PSquare::new(0.5);
+        assert_eq!(estimator.estimate(), None);
+
+        for (x, expected) in [(3., 3.), (1., 2.), (2., 2.), (5., 2.5), (4., 3.)] {
+            estimator.observe(x);
+            assert_eq!(estimator.estimate(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn matches_median_for_two_and_four_values() {
+        for values in [&[7., 2.][..], &[5., 1., 3., 2.]] {
+            let mut estimator = PSquare::new(0.5);
+            for &x in values {
+                estimator.observe(x);
+            }
+
+            assert_eq!(estimator.estimate(), Some(median(values)));
+        }
+    }
+
+    #[test]
+    fn interpolates_other_quantiles_before_the_fifth() {
+        let mut estimator = PSquare::new(0.25);
+        for x in [10., 0., 30., 20.] {
+            estimator.observe(x);
+        }
+
+        // A quarter of the way along 0, 10, 20, 30 is three quarters of the way from 0 to 10.
+        assert_eq!(estimator.estimate(), Some(7.5));
+    }
+
+    #[test]
+    fn approximates_the_median_of_shuffled_values() {
+        let mut values = (0..1001).map(|i| i as f32).collect::<Vec<_>>();
+        let mut rng = xs::from_seed(<_>::default());
+        xs::shuffle(&mut rng, &mut values);
+
+        let mut estimator = PSquare::new(0.5);
+        for &x in &values {
+            estimator.observe(x);
+        }
+
+        assert_eq!(estimator.count(), 1001);
+        let estimate = estimator.estimate().unwrap();
+        // Within 2% of the range.
+        assert!((estimate - median(&values)).abs() < 20., "{estimate}");
+    }
+
+    #[test]
+    fn approximates_other_quantiles() {
+        let mut rng = xs::from_seed(<_>::default());
+        let values = (0..2000).map(|_| xs::zero_to_one(&mut rng)).collect::<Vec<_>>();
+
+        let mut estimator = PSquare::new(0.9);
+        for &x in &values {
+            estimator.observe(x);
+        }
+
+        assert!((estimator.estimate().unwrap() - 0.9).abs() < 0.03);
+    }
+}
@@ -29,9 +29,9 @@ pub fn range(xs: &mut Xs, range: Range<u32>) -> u32 {
 
 const SCALE: u32 = 1 << f32::MANTISSA_DIGITS;
 
-#[allow(unused)]
-fn zero_to_one(xs: &mut Xs) -> f32 {
-    (range(xs, 0..SCALE + 1) as f32 / SCALE as f32) - 1.
+/// Uniformly distributed in `0.0..1.0`, so never exactly 1.
+pub fn zero_to_one(xs: &mut Xs) -> f32 {
+    range(xs, 0..SCALE) as f32 / SCALE as f32
 }
 
 #[allow(unused)]
@@ -88,3 +88,35 @@ pub fn from_seed(mut seed: Seed) -> Xs {
         wrap!(12, 13, 14, 15),
     ]
 }
+
+/// A seed for the `index`th of many independent streams derived from `seed`. The result only
+/// depends on `seed` and `index`, not on how many other streams were derived before it, or in
+/// what order, so work items can each derive their own stream, on whichever thread they run.
+pub fn split(seed: Seed, index: u64) -> Seed {
+    // Spread the index across all the bits, so that nearby indexes give unrelated seeds.
+    let mixed = u128::from_le_bytes(seed)
+        ^ (index as u128).wrapping_mul(0x9E37_79B9_7F4A_7C15_F39C_C060_5CED_C835);
+
+    let mut xs = from_seed(mixed.to_le_bytes());
+    // The first few outputs of xorshift are still closely related to the state it started from.
+    for _ in 0..16 {
+        xorshift(&mut xs);
+    }
+
+    new_seed(&mut xs)
+}
+
+#[cfg(test)]
+mod zero_to_one_works {
+    use super::*;
+
+    #[test]
+    fn stays_in_zero_to_one() {
+        let mut xs = from_seed(<_>::default());
+
+        for _ in 0..100_000 {
+            let x = zero_to_one(&mut xs);
+            assert!((0. ..1.).contains(&x), "{x}");
+        }
+    }
+}